@@ -0,0 +1,243 @@
+//! Matching a pattern against a whole list of candidates.
+
+use std::collections::BinaryHeap;
+
+use crate::rank::RankKey;
+use crate::{Match, Matcher};
+
+/// Candidates below which matching is not split across threads.
+#[cfg(feature = "parallel")]
+const MIN_CANDIDATES_PER_THREAD: usize = 1024;
+
+impl Matcher {
+    /// Matches `pattern` against every candidate and ranks the hits.
+    ///
+    /// # Arguments
+    ///
+    /// * `candidates` - The texts to search in.
+    /// * `pattern` - The pattern to search for.
+    ///
+    /// # Returns
+    ///
+    /// The index and match of every candidate that matched, from best to
+    /// worst as ordered by [`Matcher::sort_matches`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rizzer::{Matcher, MatcherConfig};
+    ///
+    /// let mut matcher = Matcher::new(MatcherConfig::default());
+    /// let hits = matcher.match_all(&["xaxbxc", "abc", "xyz"], "abc");
+    /// let order: Vec<usize> = hits.iter().map(|(i, _)| *i).collect();
+    /// assert_eq!(order, vec![1, 0]);
+    /// ```
+    pub fn match_all<S: AsRef<str>>(
+        &mut self,
+        candidates: &[S],
+        pattern: &str,
+    ) -> Vec<(usize, Match)> {
+        let mut matches = self.match_many(candidates, pattern);
+        self.sort_matches(candidates, &mut matches);
+        matches
+    }
+
+    /// Returns the `k` best matches of `pattern` among the candidates.
+    ///
+    /// Candidates are first scored without positions, keeping the best `k`
+    /// in a bounded heap, so a candidate that cannot make the cut costs no
+    /// more than computing its score. Positions are only computed for the
+    /// final `k` matches.
+    ///
+    /// # Arguments
+    ///
+    /// * `candidates` - The texts to search in.
+    /// * `pattern` - The pattern to search for.
+    /// * `k` - The maximum number of matches to return.
+    ///
+    /// # Returns
+    ///
+    /// The same matches as the first `k` returned by [`Matcher::match_all`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rizzer::{Matcher, MatcherConfig};
+    ///
+    /// let mut matcher = Matcher::new(MatcherConfig::default());
+    /// let best = matcher.top_k(&["xaxbxc", "abc", "xyz", "a_b_c"], "abc", 2);
+    /// let order: Vec<usize> = best.iter().map(|(i, _)| *i).collect();
+    /// assert_eq!(order, vec![1, 3]);
+    /// ```
+    pub fn top_k<S: AsRef<str>>(
+        &mut self,
+        candidates: &[S],
+        pattern: &str,
+        k: usize,
+    ) -> Vec<(usize, Match)> {
+        if k == 0 {
+            return vec![];
+        }
+
+        self.set_pattern(pattern);
+
+        // Criteria that look at where a match is need its positions.
+        let tiebreak = self.config().tiebreak.clone();
+        let needs_positions =
+            self.config().track_positions && tiebreak.iter().any(|t| t.needs_positions());
+
+        // A max-heap of rank keys, so the worst of the best `k` is on top. It
+        // never holds more than `k` keys, nor more than there are candidates.
+        let mut heap = BinaryHeap::with_capacity(k.min(candidates.len()));
+        for (i, text) in candidates.iter().enumerate() {
+            let text = text.as_ref();
+            let key = if needs_positions {
+                let Some(m) = self.match_text(text, true) else {
+                    continue;
+                };
+                self.rank_key(text, i, &m)
+            } else {
+                let Some(score) = self.score_text(text) else {
+                    continue;
+                };
+                RankKey::from_score(&tiebreak, text, i, score, None, self.config().graphemes())
+            };
+            if heap.len() == k {
+                match heap.peek() {
+                    Some((worst, _)) if key < *worst => {
+                        heap.pop();
+                    }
+                    _ => continue,
+                }
+            }
+            heap.push((key, i));
+        }
+
+        let track_positions = self.config().track_positions;
+        heap.into_sorted_vec()
+            .into_iter()
+            .filter_map(|(_, i)| {
+                self.match_text(candidates[i].as_ref(), track_positions)
+                    .map(|m| (i, m))
+            })
+            .collect()
+    }
+
+    /// Like [`Matcher::match_all`], but splits the candidates across threads.
+    ///
+    /// Every thread runs its own `Matcher` with the configuration of this one,
+    /// so each has its own reusable buffers. Small inputs are matched on the
+    /// calling thread. The result is the same as that of `match_all`.
+    ///
+    /// # Arguments
+    ///
+    /// * `candidates` - The texts to search in.
+    /// * `pattern` - The pattern to search for.
+    ///
+    /// # Returns
+    ///
+    /// The index and match of every candidate that matched, from best to
+    /// worst as ordered by [`Matcher::sort_matches`].
+    #[cfg(feature = "parallel")]
+    pub fn par_match_all<S: AsRef<str> + Sync>(
+        &self,
+        candidates: &[S],
+        pattern: &str,
+    ) -> Vec<(usize, Match)> {
+        let threads = std::thread::available_parallelism()
+            .map_or(1, |n| n.get())
+            .min(candidates.len() / MIN_CANDIDATES_PER_THREAD)
+            .max(1);
+        let chunk_size = candidates.len().div_ceil(threads).max(1);
+
+        let mut matches: Vec<(usize, Match)> = std::thread::scope(|scope| {
+            let handles: Vec<_> = candidates
+                .chunks(chunk_size)
+                .enumerate()
+                .map(|(chunk, candidates)| {
+                    let config = self.config().clone();
+                    scope.spawn(move || {
+                        let offset = chunk * chunk_size;
+                        Matcher::new(config)
+                            .match_many(candidates, pattern)
+                            .into_iter()
+                            .map(|(i, m)| (offset + i, m))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().expect("matcher thread panicked"))
+                .collect()
+        });
+
+        self.sort_matches(candidates, &mut matches);
+        matches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MatcherConfig;
+
+    #[test]
+    fn test_match_all_sorted() {
+        let mut matcher = Matcher::new(MatcherConfig::default());
+        let candidates = ["xaxbxc", "abc", "xyz", "a_b_c", "abc"];
+        let hits = matcher.match_all(&candidates, "abc");
+        let order: Vec<usize> = hits.iter().map(|(i, _)| *i).collect();
+        assert_eq!(order, vec![1, 4, 3, 0]);
+        assert!(hits.windows(2).all(|w| w[0].1.score >= w[1].1.score));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_par_match_all() {
+        use crate::{PathMode, Scoring};
+
+        let mut matcher = Matcher::new(MatcherConfig {
+            scoring: Scoring::path(),
+            path_mode: PathMode::Unix,
+            ..Default::default()
+        });
+        let candidates: Vec<String> = (0..10_000)
+            .map(|i| format!("src/module_{}/file_{}.rs", i % 97, i))
+            .collect();
+        let expected = matcher.match_all(&candidates, "m9f7");
+        assert!(!expected.is_empty());
+        assert_eq!(matcher.par_match_all(&candidates, "m9f7"), expected);
+    }
+
+    #[test]
+    fn test_top_k_matches_match_all() {
+        let mut matcher = Matcher::new(MatcherConfig::default());
+        let candidates: Vec<String> = (0..500)
+            .map(|i| format!("item_{}_{}", i % 7, i * 31 % 101))
+            .collect();
+        let all = matcher.match_all(&candidates, "i3_1");
+        assert!(all.len() > 10);
+
+        for k in [0, 1, 5, 10, all.len(), all.len() + 5, usize::MAX] {
+            let top = matcher.top_k(&candidates, "i3_1", k);
+            assert_eq!(top, all[..k.min(all.len())], "k = {k}");
+        }
+    }
+
+    #[test]
+    fn test_top_k_positional_tiebreak() {
+        use crate::Tiebreak;
+
+        let mut matcher = Matcher::new(MatcherConfig {
+            tiebreak: vec![Tiebreak::Score, Tiebreak::Begin],
+            ..Default::default()
+        });
+        let candidates = ["xx foo", "x foo", "foo", "xxx foo"];
+        let all = matcher.match_all(&candidates, "foo");
+        let order: Vec<usize> = all.iter().map(|(i, _)| *i).collect();
+        assert_eq!(order, vec![2, 1, 0, 3]);
+        assert_eq!(matcher.top_k(&candidates, "foo", 2), all[..2]);
+    }
+}
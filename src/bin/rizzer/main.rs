@@ -0,0 +1,385 @@
+//! Filters lines of standard input by a fuzzy query, like `fzf --filter`.
+//!
+//! Matching lines are printed from best to worst. The exit status is 0 if at
+//! least one line matched, 1 if none did and 2 on error. With `--interactive`,
+//! lines are picked in a full-screen picker instead, and the exit status is
+//! 130 if the picker was aborted.
+
+mod picker;
+
+use std::borrow::Cow;
+use std::io::{self, BufWriter, Read, Write};
+use std::process::ExitCode;
+
+use lexopt::prelude::*;
+use picker::Picker;
+use rizzer::{CaseMatching, Match, Matcher, MatcherConfig, Normalization, Query};
+
+const USAGE: &str = "\
+Usage: rizzer [OPTIONS] <QUERY>
+       rizzer --interactive [OPTIONS] [QUERY]
+
+Reads lines from standard input and prints those matching QUERY, best first.
+
+Options:
+  -i, --interactive          Pick lines in a full-screen picker, starting with QUERY
+      --case <MODE>          Case matching: respect, ignore or smart [default: smart]
+      --normalize <LEVEL>    Normalization: none, case-fold, strip-diacritics,
+                             compatibility or transliterate [default: strip-diacritics]
+  -x, --extended             Parse QUERY with the extended search syntax
+  -n, --limit <N>            Print at most N matches
+      --print-score          Print the score of every match before it, separated by a tab
+      --print-positions      Print the matched character positions after every match,
+                             separated by a tab
+      --read0                Read input delimited by NUL instead of newline
+      --print0               Print output delimited by NUL instead of newline
+  -h, --help                 Print this help
+  -V, --version              Print the version
+";
+
+/// The parsed command-line arguments.
+#[derive(Debug, Clone, PartialEq)]
+struct Args {
+    query: String,
+    interactive: bool,
+    case_matching: CaseMatching,
+    normalization: Normalization,
+    extended: bool,
+    limit: Option<usize>,
+    print_score: bool,
+    print_positions: bool,
+    read0: bool,
+    print0: bool,
+}
+
+/// What the command line asks for.
+#[derive(Debug, Clone, PartialEq)]
+enum Command {
+    Filter(Args),
+    Help,
+    Version,
+}
+
+/// Parses the command-line arguments, without the program name.
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Command, lexopt::Error> {
+    let mut parser = lexopt::Parser::from_args(args);
+    let mut query = None;
+    let mut args = Args {
+        query: String::new(),
+        interactive: false,
+        case_matching: CaseMatching::Smart,
+        normalization: Normalization::default(),
+        extended: false,
+        limit: None,
+        print_score: false,
+        print_positions: false,
+        read0: false,
+        print0: false,
+    };
+
+    while let Some(arg) = parser.next()? {
+        match arg {
+            Long("case") => args.case_matching = parse_case(&parser.value()?.string()?)?,
+            Long("normalize") => {
+                args.normalization = parse_normalization(&parser.value()?.string()?)?
+            }
+            Short('i') | Long("interactive") => args.interactive = true,
+            Short('x') | Long("extended") => args.extended = true,
+            Short('n') | Long("limit") => args.limit = Some(parser.value()?.parse()?),
+            Long("print-score") => args.print_score = true,
+            Long("print-positions") => args.print_positions = true,
+            Long("read0") => args.read0 = true,
+            Long("print0") => args.print0 = true,
+            Short('h') | Long("help") => return Ok(Command::Help),
+            Short('V') | Long("version") => return Ok(Command::Version),
+            Value(value) if query.is_none() => query = Some(value.string()?),
+            _ => return Err(arg.unexpected()),
+        }
+    }
+
+    args.query = match query {
+        Some(query) => query,
+        None if args.interactive => String::new(),
+        None => return Err("missing argument QUERY".into()),
+    };
+    if args.interactive {
+        // These only affect the output of the filter.
+        let unsupported = [
+            (args.extended, "--extended"),
+            (args.limit.is_some(), "--limit"),
+            (args.print_score, "--print-score"),
+            (args.print_positions, "--print-positions"),
+        ];
+        if let Some((_, flag)) = unsupported.iter().find(|(set, _)| *set) {
+            return Err(format!("{flag} is not supported with --interactive").into());
+        }
+    }
+    Ok(Command::Filter(args))
+}
+
+/// Parses the value of `--case`.
+fn parse_case(value: &str) -> Result<CaseMatching, lexopt::Error> {
+    match value {
+        "respect" => Ok(CaseMatching::Respect),
+        "ignore" => Ok(CaseMatching::Ignore),
+        "smart" => Ok(CaseMatching::Smart),
+        _ => Err(format!("invalid case mode '{value}'").into()),
+    }
+}
+
+/// Parses the value of `--normalize`.
+fn parse_normalization(value: &str) -> Result<Normalization, lexopt::Error> {
+    match value {
+        "none" => Ok(Normalization::None),
+        "case-fold" => Ok(Normalization::CaseFold),
+        "strip-diacritics" => Ok(Normalization::StripDiacritics),
+        "compatibility" => Ok(Normalization::Compatibility),
+        "transliterate" if !cfg!(feature = "transliterate") => {
+            Err("rizzer was built without the transliterate feature".into())
+        }
+        "transliterate" => Ok(Normalization::Transliterate),
+        _ => Err(format!("invalid normalization '{value}'").into()),
+    }
+}
+
+/// Splits the input into lines, dropping a trailing delimiter.
+///
+/// The input is split as raw bytes, so lines that are not valid UTF-8, such
+/// as file names from `find -print0`, can be printed back unchanged.
+fn split_lines(input: &[u8], delimiter: u8) -> Vec<&[u8]> {
+    let input = input.strip_suffix(&[delimiter]).unwrap_or(input);
+    if input.is_empty() {
+        return vec![];
+    }
+    input.split(|&byte| byte == delimiter).collect()
+}
+
+/// Creates the matcher configured by `args`.
+fn matcher(args: &Args) -> Matcher {
+    Matcher::new(MatcherConfig {
+        case_matching: args.case_matching,
+        normalization: args.normalization,
+        ..Default::default()
+    })
+}
+
+/// Ranks the lines matching the query of `args`.
+fn filter<S: AsRef<str>>(lines: &[S], args: &Args) -> Vec<(usize, Match)> {
+    let mut matcher = matcher(args);
+    let limit = args.limit.unwrap_or(usize::MAX);
+
+    if args.extended {
+        let query = Query::parse(&args.query);
+        let mut matches: Vec<(usize, Match)> = lines
+            .iter()
+            .enumerate()
+            .filter_map(|(i, line)| matcher.match_query(line.as_ref(), &query).map(|m| (i, m)))
+            .collect();
+        matcher.sort_matches(lines, &mut matches);
+        matches.truncate(limit);
+        matches
+    } else if args.limit.is_some() {
+        matcher.top_k(lines, &args.query, limit)
+    } else {
+        matcher.match_all(lines, &args.query)
+    }
+}
+
+/// Writes one match as an output line.
+///
+/// `line` is the line as read, and the positions refer to the characters of
+/// its lossy UTF-8 view, which was matched.
+fn write_match(out: &mut impl Write, line: &[u8], m: &Match, args: &Args) -> io::Result<()> {
+    if args.print_score {
+        write!(out, "{}\t", m.score)?;
+    }
+    out.write_all(line)?;
+    if args.print_positions {
+        let positions: Vec<String> = m.positions.iter().map(usize::to_string).collect();
+        write!(out, "\t{}", positions.join(","))?;
+    }
+    out.write_all(if args.print0 { b"\0" } else { b"\n" })
+}
+
+/// The outcome of a run.
+enum Outcome {
+    /// At least one line was printed.
+    Matched,
+    /// No line matched, or no line was picked.
+    NoMatch,
+    /// The picker was aborted.
+    Aborted,
+}
+
+/// Filters standard input into standard output.
+fn run(args: &Args) -> io::Result<Outcome> {
+    let mut input = Vec::new();
+    io::stdin().lock().read_to_end(&mut input)?;
+    let lines = split_lines(&input, if args.read0 { b'\0' } else { b'\n' });
+    // Invalid UTF-8 is only replaced for matching and display; the original
+    // bytes of a line are printed.
+    let views: Vec<Cow<str>> = lines
+        .iter()
+        .map(|line| String::from_utf8_lossy(line))
+        .collect();
+
+    if args.interactive {
+        return pick(&lines, &views, args);
+    }
+
+    let matches = filter(&views, args);
+    let mut out = BufWriter::new(io::stdout().lock());
+    for (i, m) in &matches {
+        write_match(&mut out, lines[*i], m, args)?;
+    }
+    out.flush()?;
+
+    Ok(if matches.is_empty() {
+        Outcome::NoMatch
+    } else {
+        Outcome::Matched
+    })
+}
+
+/// Lets the user pick lines interactively, and prints the picked lines.
+///
+/// # Arguments
+///
+/// * `lines` - The lines as read.
+/// * `views` - The lossy UTF-8 view of every line, which is matched and shown.
+/// * `args` - The parsed arguments.
+fn pick(lines: &[&[u8]], views: &[Cow<str>], args: &Args) -> io::Result<Outcome> {
+    let views: Vec<&str> = views.iter().map(|view| view.as_ref()).collect();
+    let Some(picked) = Picker::new(matcher(args), &views, &args.query).run()? else {
+        return Ok(Outcome::Aborted);
+    };
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    for i in &picked {
+        out.write_all(lines[*i])?;
+        out.write_all(if args.print0 { b"\0" } else { b"\n" })?;
+    }
+    out.flush()?;
+
+    Ok(if picked.is_empty() {
+        Outcome::NoMatch
+    } else {
+        Outcome::Matched
+    })
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(Command::Filter(args)) => args,
+        Ok(Command::Help) => {
+            print!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Ok(Command::Version) => {
+            println!("rizzer {}", env!("CARGO_PKG_VERSION"));
+            return ExitCode::SUCCESS;
+        }
+        Err(err) => {
+            eprintln!("rizzer: {err}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(&args) {
+        Ok(Outcome::Matched) => ExitCode::SUCCESS,
+        Ok(Outcome::NoMatch) => ExitCode::from(1),
+        Ok(Outcome::Aborted) => ExitCode::from(130),
+        // The reader of the output went away, e.g. `rizzer foo | head -1`.
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("rizzer: {err}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, lexopt::Error> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let Ok(Command::Filter(args)) = parse(&[
+            "--case=respect",
+            "-n",
+            "5",
+            "--print-score",
+            "--read0",
+            "--normalize",
+            "compatibility",
+            "foo",
+        ]) else {
+            panic!("expected a filter command");
+        };
+        assert_eq!(args.query, "foo");
+        assert_eq!(args.case_matching, CaseMatching::Respect);
+        assert_eq!(args.normalization, Normalization::Compatibility);
+        assert_eq!(args.limit, Some(5));
+        assert!(args.print_score && args.read0);
+        assert!(!args.print_positions && !args.print0);
+
+        assert_eq!(parse(&["-h"]).unwrap(), Command::Help);
+        assert!(parse(&[]).is_err());
+        assert!(matches!(parse(&["-i"]), Ok(Command::Filter(args)) if args.query.is_empty()));
+        assert!(parse(&["-i", "-x", "foo"]).is_err());
+        assert!(parse(&["-i", "-n", "5"]).is_err());
+        assert!(parse(&["-i", "--print-score"]).is_err());
+        assert!(parse(&["-i", "--print-positions"]).is_err());
+        assert!(matches!(
+            parse(&["-i", "--read0", "--print0"]),
+            Ok(Command::Filter(_))
+        ));
+        assert!(parse(&["foo", "bar"]).is_err());
+        assert!(parse(&["--case", "upper", "foo"]).is_err());
+        assert!(parse(&["--limit", "ten", "foo"]).is_err());
+    }
+
+    #[test]
+    fn test_filter_and_output() {
+        let lines = split_lines(b"xaxbxc\nabc\nxyz\n", b'\n');
+        assert_eq!(lines, vec![&b"xaxbxc"[..], b"abc", b"xyz"]);
+        assert!(split_lines(b"", b'\n').is_empty());
+
+        let Ok(Command::Filter(args)) = parse(&["--print-score", "--print-positions", "abc"])
+        else {
+            panic!("expected a filter command");
+        };
+        let views: Vec<Cow<str>> = lines.iter().map(|l| String::from_utf8_lossy(l)).collect();
+        let matches = filter(&views, &args);
+        let order: Vec<usize> = matches.iter().map(|(i, _)| *i).collect();
+        assert_eq!(order, vec![1, 0]);
+
+        let mut out = Vec::new();
+        write_match(&mut out, lines[1], &matches[0].1, &args).unwrap();
+        let score = matches[0].1.score;
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!("{score}\tabc\t0,1,2\n")
+        );
+    }
+
+    #[test]
+    fn test_invalid_utf8_printed_unchanged() {
+        let input = b"caf\xe9.txt\0other\0";
+        let lines = split_lines(input, b'\0');
+        let views: Vec<Cow<str>> = lines.iter().map(|l| String::from_utf8_lossy(l)).collect();
+        let Ok(Command::Filter(args)) = parse(&["--read0", "--print0", "caf"]) else {
+            panic!("expected a filter command");
+        };
+        let matches = filter(&views, &args);
+        assert_eq!(matches.len(), 1);
+
+        let mut out = Vec::new();
+        write_match(&mut out, lines[matches[0].0], &matches[0].1, &args).unwrap();
+        assert_eq!(out, b"caf\xe9.txt\0");
+    }
+}
@@ -0,0 +1,310 @@
+//! The interactive full-screen picker.
+//!
+//! The picker is drawn on standard error, so standard input can carry the
+//! candidates and standard output the selection. Keys are read from the
+//! terminal, even when standard input is a pipe.
+
+use std::collections::BTreeSet;
+use std::io::{self, Write};
+
+use crossterm::cursor::MoveTo;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Color, Print, ResetColor, SetAttribute, SetForegroundColor};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use rizzer::{Matcher, Session};
+
+/// Rows above the list: the query line and the info line.
+const HEADER_ROWS: usize = 2;
+
+/// What to do after a key was handled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Action {
+    /// Keep reading keys.
+    Continue,
+    /// Print the candidates with these indices and exit.
+    Accept(Vec<usize>),
+    /// Exit without printing anything.
+    Abort,
+}
+
+/// The state of the picker.
+pub(crate) struct Picker<'a> {
+    lines: &'a [&'a str],
+    session: Session<&'a str>,
+    query: String,
+    /// The row of the ranked list under the cursor.
+    cursor: usize,
+    /// The first row of the ranked list that is shown.
+    scroll: usize,
+    /// The indices of the selected candidates.
+    selected: BTreeSet<usize>,
+}
+
+impl<'a> Picker<'a> {
+    /// Creates a picker over `lines`, starting with `query`.
+    pub(crate) fn new(matcher: Matcher, lines: &'a [&'a str], query: &str) -> Self {
+        Picker {
+            lines,
+            session: Session::new(matcher, lines.to_vec()),
+            query: query.to_string(),
+            cursor: 0,
+            scroll: 0,
+            selected: BTreeSet::new(),
+        }
+    }
+
+    /// Runs the picker until a selection is accepted or the picker is
+    /// aborted.
+    ///
+    /// # Returns
+    ///
+    /// The indices of the accepted candidates, or `None` if aborted.
+    pub(crate) fn run(mut self) -> io::Result<Option<Vec<usize>>> {
+        let mut tty = io::stderr();
+        let _guard = TerminalGuard::enter()?;
+
+        loop {
+            let (width, height) = terminal::size()?;
+            self.draw(&mut tty, width as usize, height as usize)?;
+
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind == KeyEventKind::Release {
+                continue;
+            }
+            match self.handle_key(key) {
+                Action::Continue => {}
+                Action::Accept(indices) => return Ok(Some(indices)),
+                Action::Abort => return Ok(None),
+            }
+        }
+    }
+
+    /// Updates the state for a key press.
+    pub(crate) fn handle_key(&mut self, key: KeyEvent) -> Action {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return Action::Abort,
+            KeyCode::Char('c' | 'g') if ctrl => return Action::Abort,
+            KeyCode::Enter => return Action::Accept(self.accepted()),
+            KeyCode::Up => self.move_cursor(-1),
+            KeyCode::Char('p' | 'k') if ctrl => self.move_cursor(-1),
+            KeyCode::Down => self.move_cursor(1),
+            KeyCode::Char('n' | 'j') if ctrl => self.move_cursor(1),
+            KeyCode::Tab => {
+                self.toggle();
+                self.move_cursor(1);
+            }
+            KeyCode::BackTab => {
+                self.toggle();
+                self.move_cursor(-1);
+            }
+            KeyCode::Char('u') if ctrl => self.set_query(String::new()),
+            KeyCode::Backspace => {
+                let mut query = std::mem::take(&mut self.query);
+                query.pop();
+                self.set_query(query);
+            }
+            KeyCode::Char(c) if !ctrl => {
+                let mut query = std::mem::take(&mut self.query);
+                query.push(c);
+                self.set_query(query);
+            }
+            _ => {}
+        }
+        Action::Continue
+    }
+
+    /// Replaces the query, moving the cursor back to the best match.
+    fn set_query(&mut self, query: String) {
+        self.query = query;
+        self.cursor = 0;
+        self.scroll = 0;
+    }
+
+    /// Moves the cursor by `delta` rows, staying within the ranked list.
+    fn move_cursor(&mut self, delta: isize) {
+        let len = self.session.search(&self.query).len();
+        self.cursor = self
+            .cursor
+            .saturating_add_signed(delta)
+            .min(len.saturating_sub(1));
+    }
+
+    /// Toggles the selection of the candidate under the cursor.
+    fn toggle(&mut self) {
+        if let Some(&(index, _)) = self.session.search(&self.query).get(self.cursor) {
+            if !self.selected.remove(&index) {
+                self.selected.insert(index);
+            }
+        }
+    }
+
+    /// Returns the candidates to print when the selection is accepted: the
+    /// selected candidates in input order, or else the one under the cursor.
+    fn accepted(&mut self) -> Vec<usize> {
+        if !self.selected.is_empty() {
+            return self.selected.iter().copied().collect();
+        }
+        let results = self.session.search(&self.query);
+        results
+            .get(self.cursor)
+            .map(|&(index, _)| index)
+            .into_iter()
+            .collect()
+    }
+
+    /// Draws the query line, the info line and the visible part of the
+    /// ranked list.
+    fn draw(&mut self, out: &mut impl Write, width: usize, height: usize) -> io::Result<()> {
+        let rows = height.saturating_sub(HEADER_ROWS).max(1);
+        if self.cursor < self.scroll {
+            self.scroll = self.cursor;
+        } else if self.cursor >= self.scroll + rows {
+            self.scroll = self.cursor + 1 - rows;
+        }
+
+        let (lines, selected) = (self.lines, &self.selected);
+        let results = self.session.search(&self.query);
+
+        queue!(out, Clear(ClearType::All), MoveTo(0, 1))?;
+        let mut info = format!("  {}/{}", results.len(), lines.len());
+        if !selected.is_empty() {
+            info.push_str(&format!(" ({})", selected.len()));
+        }
+        queue!(
+            out,
+            SetForegroundColor(Color::DarkGrey),
+            Print(truncate(&info, width)),
+            ResetColor
+        )?;
+
+        for (row, (index, m)) in results.iter().enumerate().skip(self.scroll).take(rows) {
+            let is_cursor = row == self.cursor;
+            queue!(out, MoveTo(0, (row - self.scroll + HEADER_ROWS) as u16))?;
+            if is_cursor {
+                queue!(out, SetAttribute(Attribute::Bold))?;
+            }
+            let marker = if selected.contains(index) { '*' } else { ' ' };
+            queue!(
+                out,
+                Print(if is_cursor { '>' } else { ' ' }),
+                SetForegroundColor(Color::Magenta),
+                Print(marker),
+                ResetColor
+            )?;
+
+            let mut positions = m.positions.iter().peekable();
+            for (i, c) in lines[*index]
+                .chars()
+                .take(width.saturating_sub(2))
+                .enumerate()
+            {
+                let c = if c.is_control() { ' ' } else { c };
+                if positions.next_if_eq(&&i).is_some() {
+                    queue!(out, SetForegroundColor(Color::Green), Print(c), ResetColor)?;
+                } else {
+                    queue!(out, Print(c))?;
+                }
+            }
+            queue!(out, SetAttribute(Attribute::Reset))?;
+        }
+
+        let prompt = format!("> {}", self.query);
+        queue!(out, MoveTo(0, 0), Print(truncate(&prompt, width)))?;
+        out.flush()
+    }
+}
+
+/// Truncates `text` to at most `width` characters.
+fn truncate(text: &str, width: usize) -> &str {
+    match text.char_indices().nth(width) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    }
+}
+
+/// Puts the terminal in raw mode on an alternate screen, and restores it
+/// when dropped, including on panic.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stderr(), EnterAlternateScreen)?;
+        Ok(TerminalGuard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(io::stderr(), LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rizzer::MatcherConfig;
+
+    fn press(picker: &mut Picker, code: KeyCode) -> Action {
+        picker.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn ctrl(picker: &mut Picker, c: char) -> Action {
+        picker.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL))
+    }
+
+    #[test]
+    fn test_picker_keys() {
+        let lines = ["src/lib.rs", "src/matcher.rs", "README.md", "src/batch.rs"];
+        let mut picker = Picker::new(Matcher::new(MatcherConfig::default()), &lines, "");
+
+        for c in "src".chars() {
+            press(&mut picker, KeyCode::Char(c));
+        }
+        assert_eq!(picker.query, "src");
+        assert_eq!(press(&mut picker, KeyCode::Enter), Action::Accept(vec![0]));
+
+        // The cursor stays within the list.
+        ctrl(&mut picker, 'n');
+        press(&mut picker, KeyCode::Down);
+        press(&mut picker, KeyCode::Down);
+        assert_eq!(picker.cursor, 2);
+        ctrl(&mut picker, 'p');
+        assert_eq!(picker.cursor, 1);
+
+        press(&mut picker, KeyCode::Backspace);
+        assert_eq!((picker.query.as_str(), picker.cursor), ("sr", 0));
+        ctrl(&mut picker, 'u');
+        assert_eq!(picker.query, "");
+        assert_eq!(ctrl(&mut picker, 'c'), Action::Abort);
+    }
+
+    #[test]
+    fn test_picker_multi_select() {
+        let lines = ["alpha", "beta", "gamma"];
+        let mut picker = Picker::new(Matcher::new(MatcherConfig::default()), &lines, "a");
+        let ranked: Vec<usize> = picker.session.search("a").iter().map(|(i, _)| *i).collect();
+
+        // Tab selects and moves down, Shift-Tab toggles and moves up.
+        press(&mut picker, KeyCode::Tab);
+        press(&mut picker, KeyCode::Tab);
+        press(&mut picker, KeyCode::BackTab);
+        press(&mut picker, KeyCode::BackTab);
+        assert_eq!(picker.selected, BTreeSet::from([ranked[0], ranked[2]]));
+
+        let mut expected = vec![ranked[0], ranked[2]];
+        expected.sort();
+        assert_eq!(press(&mut picker, KeyCode::Enter), Action::Accept(expected));
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("héllo", 2), "hé");
+        assert_eq!(truncate("hi", 5), "hi");
+    }
+}
@@ -0,0 +1,489 @@
+//! Matching against selected fields of a line, like fzf's `--nth` and
+//! `--delimiter` options.
+//!
+//! A line is split into fields by a [`Delimiter`], and only the fields picked
+//! by a [`Fields`] selection are matched. The positions of the resulting
+//! match still refer to the characters of the whole line, so the line can be
+//! displayed and highlighted as a whole.
+//!
+//! ```
+//! use rizzer::fields::{Delimiter, Fields};
+//! use rizzer::{Matcher, MatcherConfig};
+//!
+//! let fields = Fields::parse("2", Delimiter::parse("\t")).unwrap();
+//! let mut matcher = Matcher::new(MatcherConfig::default());
+//! let m = matcher.match_fields("42\tmatcher\tsrc/matcher.rs", "mat", &fields).unwrap();
+//! assert_eq!(m.positions, vec![3, 4, 5]);
+//! assert!(matcher.match_fields("42\tlib\tsrc/matcher.rs", "mat", &fields).is_none());
+//! ```
+
+use std::fmt;
+use std::ops::Range;
+
+use crate::text::units;
+use crate::{Match, Matcher, Unit};
+
+/// What separates the fields of a line.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Delimiter(DelimiterKind);
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+enum DelimiterKind {
+    /// Runs of whitespace, ignoring leading and trailing whitespace.
+    #[default]
+    Whitespace,
+    /// A literal string.
+    Str(String),
+    /// Any single character of a class.
+    Class(CharSet),
+}
+
+impl Delimiter {
+    /// Fields separated by runs of whitespace, as in AWK. This is the default.
+    pub fn whitespace() -> Self {
+        Delimiter(DelimiterKind::Whitespace)
+    }
+
+    /// Fields separated by the literal string `delimiter`.
+    pub fn string(delimiter: &str) -> Self {
+        if delimiter.is_empty() {
+            return Delimiter::whitespace();
+        }
+        Delimiter(DelimiterKind::Str(delimiter.to_string()))
+    }
+
+    /// Parses a delimiter.
+    ///
+    /// A delimiter enclosed in brackets is a character class, such as `[,;]`,
+    /// `[ \t]` or `[^a-z0-9]`, and any single character of the class
+    /// separates fields. Within a class, `a-z` denotes a range, a leading `^`
+    /// negates the class, and `\t`, `\n`, `\\`, `\]` and `\-` are escapes.
+    /// Anything else is a literal string, as with [`Delimiter::string`].
+    pub fn parse(delimiter: &str) -> Self {
+        match delimiter
+            .strip_prefix('[')
+            .and_then(|class| class.strip_suffix(']'))
+        {
+            Some(class) if !class.is_empty() => {
+                Delimiter(DelimiterKind::Class(CharSet::parse(class)))
+            }
+            _ => Delimiter::string(delimiter),
+        }
+    }
+
+    /// Splits `line` into the byte ranges of its fields.
+    fn split(&self, line: &str) -> Vec<Range<usize>> {
+        let mut fields = Vec::new();
+        match &self.0 {
+            DelimiterKind::Whitespace => {
+                let mut start = None;
+                for (i, c) in line.char_indices() {
+                    match (start, c.is_whitespace()) {
+                        (None, false) => start = Some(i),
+                        (Some(s), true) => {
+                            fields.push(s..i);
+                            start = None;
+                        }
+                        _ => {}
+                    }
+                }
+                if let Some(s) = start {
+                    fields.push(s..line.len());
+                }
+            }
+            DelimiterKind::Str(delimiter) => {
+                let mut start = 0;
+                for (i, _) in line.match_indices(delimiter.as_str()) {
+                    fields.push(start..i);
+                    start = i + delimiter.len();
+                }
+                fields.push(start..line.len());
+            }
+            DelimiterKind::Class(class) => {
+                let mut start = 0;
+                for (i, c) in line.char_indices() {
+                    if class.contains(c) {
+                        fields.push(start..i);
+                        start = i + c.len_utf8();
+                    }
+                }
+                fields.push(start..line.len());
+            }
+        }
+        fields
+    }
+}
+
+/// A set of characters, as written between the brackets of a class.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct CharSet {
+    ranges: Vec<(char, char)>,
+    negated: bool,
+}
+
+impl CharSet {
+    fn parse(class: &str) -> Self {
+        let (class, negated) = match class.strip_prefix('^') {
+            Some(rest) if !rest.is_empty() => (rest, true),
+            _ => (class, false),
+        };
+
+        let mut chars = Vec::new();
+        let mut iter = class.chars();
+        while let Some(c) = iter.next() {
+            // Escaped characters are marked so that `\-` is not a range.
+            chars.push(match c {
+                '\\' => match iter.next() {
+                    Some('t') => ('\t', true),
+                    Some('n') => ('\n', true),
+                    Some(c) => (c, true),
+                    None => ('\\', true),
+                },
+                c => (c, false),
+            });
+        }
+
+        let mut ranges = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let (c, _) = chars[i];
+            match chars.get(i + 1..i + 3) {
+                Some(&[('-', false), (end, _)]) => {
+                    ranges.push((c, end));
+                    i += 3;
+                }
+                _ => {
+                    ranges.push((c, c));
+                    i += 1;
+                }
+            }
+        }
+
+        CharSet { ranges, negated }
+    }
+
+    fn contains(&self, c: char) -> bool {
+        let found = self
+            .ranges
+            .iter()
+            .any(|&(start, end)| start <= c && c <= end);
+        found != self.negated
+    }
+}
+
+/// A range of fields, with 1-based indices. Negative indices count from the
+/// last field, which is `-1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldRange {
+    /// The first field of the range, or `None` for the first field of the
+    /// line.
+    pub start: Option<isize>,
+    /// The last field of the range, inclusive, or `None` for the last field
+    /// of the line.
+    pub end: Option<isize>,
+}
+
+impl FieldRange {
+    /// Parses a range written as `N`, `N..M`, `N..`, `..M` or `..`, where `N`
+    /// and `M` are non-zero integers.
+    pub fn parse(range: &str) -> Result<Self, ParseFieldsError> {
+        let index = |s: &str| -> Result<Option<isize>, ParseFieldsError> {
+            if s.is_empty() {
+                return Ok(None);
+            }
+            match s.parse::<isize>() {
+                Ok(0) | Err(_) => Err(ParseFieldsError(range.to_string())),
+                Ok(i) => Ok(Some(i)),
+            }
+        };
+
+        match range.split_once("..") {
+            Some((start, end)) => Ok(FieldRange {
+                start: index(start)?,
+                end: index(end)?,
+            }),
+            None if !range.is_empty() => {
+                let i = index(range)?;
+                Ok(FieldRange { start: i, end: i })
+            }
+            None => Err(ParseFieldsError(range.to_string())),
+        }
+    }
+
+    /// Resolves the range for a line of `count` fields.
+    ///
+    /// # Returns
+    ///
+    /// The 0-based indices of the fields in the range, which may be empty.
+    fn resolve(&self, count: usize) -> Range<usize> {
+        let count = count as isize;
+        let resolve = |i: isize| if i < 0 { count + i } else { i - 1 };
+        let start = self.start.map_or(0, resolve).max(0);
+        let end = self.end.map_or(count - 1, resolve).min(count - 1);
+        if start > end {
+            return 0..0;
+        }
+        start as usize..end as usize + 1
+    }
+}
+
+/// An error from parsing a field range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseFieldsError(String);
+
+impl fmt::Display for ParseFieldsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid field range '{}'", self.0)
+    }
+}
+
+impl std::error::Error for ParseFieldsError {}
+
+/// A selection of the fields of a line to match against.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Fields {
+    delimiter: Delimiter,
+    /// The ranges of fields to match. An empty list selects the whole line.
+    ranges: Vec<FieldRange>,
+}
+
+impl Fields {
+    /// Creates a selection of the fields in `ranges`, split by `delimiter`.
+    pub fn new(delimiter: Delimiter, ranges: Vec<FieldRange>) -> Self {
+        Fields { delimiter, ranges }
+    }
+
+    /// Parses a comma-separated list of field ranges, such as `1,3..4` or
+    /// `-1`. See [`FieldRange::parse`] for the syntax of a range.
+    pub fn parse(nth: &str, delimiter: Delimiter) -> Result<Self, ParseFieldsError> {
+        let ranges = nth
+            .split(',')
+            .map(FieldRange::parse)
+            .collect::<Result<_, _>>()?;
+        Ok(Fields::new(delimiter, ranges))
+    }
+
+    /// Extracts the selected fields of `line`.
+    ///
+    /// Selected fields are joined with a space, in the order of the line.
+    /// A field selected by several ranges is included once.
+    pub fn select(&self, line: &str) -> Selection {
+        if self.ranges.is_empty() {
+            return Selection {
+                text: line.to_string(),
+                sources: (0..line.chars().count()).map(Some).collect(),
+            };
+        }
+
+        let fields = self.delimiter.split(line);
+        let mut selected = vec![false; fields.len()];
+        for range in &self.ranges {
+            for i in range.resolve(fields.len()) {
+                selected[i] = true;
+            }
+        }
+
+        let mut selection = Selection::default();
+        let (mut index, mut byte) = (0, 0);
+        for (field, _) in fields.iter().zip(selected).filter(|(_, s)| *s) {
+            // Count the characters of the line before the field.
+            index += line[byte..field.start].chars().count();
+
+            if !selection.text.is_empty() {
+                selection.text.push(' ');
+                selection.sources.push(None);
+            }
+            for c in line[field.clone()].chars() {
+                selection.text.push(c);
+                selection.sources.push(Some(index));
+                index += 1;
+            }
+            byte = field.end;
+        }
+        selection
+    }
+}
+
+/// The selected fields of a line, with a map back to the line.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Selection {
+    text: String,
+    /// The index in the line of every character of `text`, or `None` for the
+    /// spaces joining fields.
+    sources: Vec<Option<usize>>,
+}
+
+impl Selection {
+    /// Returns the selected fields, joined with a space.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Translates a match against [`Selection::text`] into a match against
+    /// the whole line.
+    ///
+    /// The positions of the result are always character indices. For a match
+    /// in grapheme clusters, every character of a matched cluster is matched.
+    /// Matched spaces joining two fields have no counterpart in the line and
+    /// are dropped from the positions.
+    pub fn to_line(&self, m: Match) -> Match {
+        let chars = match m.unit {
+            Unit::Char => m.positions,
+            Unit::Grapheme => {
+                let mut positions = m.positions.iter().peekable();
+                let mut chars = Vec::with_capacity(positions.len());
+                let mut start = 0;
+                for (index, cluster) in units(&self.text, true).enumerate() {
+                    let len = cluster.chars().count();
+                    if positions.next_if_eq(&&index).is_some() {
+                        chars.extend(start..start + len);
+                    }
+                    start += len;
+                }
+                chars
+            }
+        };
+        let positions: Vec<usize> = chars
+            .iter()
+            .filter_map(|&p| self.sources.get(p).copied().flatten())
+            .collect();
+        match (positions.first(), positions.last()) {
+            (Some(&start), Some(&end)) => Match {
+                start,
+                end: end + 1,
+                score: m.score,
+                positions,
+                unit: Unit::Char,
+            },
+            _ => Match {
+                start: 0,
+                end: 0,
+                score: m.score,
+                positions,
+                unit: Unit::Char,
+            },
+        }
+    }
+}
+
+impl Matcher {
+    /// Matches `pattern` against the fields of `line` selected by `fields`.
+    ///
+    /// Positions are translated back to characters of the whole line, as by
+    /// [`Selection::to_line`]. They are always character indices, even with
+    /// `MatcherConfig::graphemes`.
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - The line to search in.
+    /// * `pattern` - The pattern to search for.
+    /// * `fields` - The fields of the line to match against.
+    ///
+    /// # Returns
+    ///
+    /// `Some(Match)` if `pattern` matches the selected fields, `None`
+    /// otherwise.
+    pub fn match_fields(&mut self, line: &str, pattern: &str, fields: &Fields) -> Option<Match> {
+        let selection = fields.select(line);
+        let m = self.match_one(selection.text(), pattern)?;
+        Some(selection.to_line(m))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MatcherConfig;
+
+    fn split<'a>(delimiter: &Delimiter, line: &'a str) -> Vec<&'a str> {
+        delimiter
+            .split(line)
+            .into_iter()
+            .map(|range| &line[range])
+            .collect()
+    }
+
+    #[test]
+    fn test_split() {
+        let whitespace = Delimiter::default();
+        assert_eq!(split(&whitespace, "  a  b\tc "), vec!["a", "b", "c"]);
+        assert_eq!(split(&Delimiter::parse("::"), "a::b::"), vec!["a", "b", ""]);
+        assert_eq!(
+            split(&Delimiter::parse("[,;]"), "a,b;;c"),
+            vec!["a", "b", "", "c"]
+        );
+        assert_eq!(
+            split(&Delimiter::parse("[^a-z]"), "ab1cd-e"),
+            vec!["ab", "cd", "e"]
+        );
+        assert_eq!(split(&Delimiter::parse("[\\t]"), "a\tb"), vec!["a", "b"]);
+        assert_eq!(
+            split(&Delimiter::parse("[a\\-]"), "xa-b"),
+            vec!["x", "", "b"]
+        );
+    }
+
+    #[test]
+    fn test_parse_ranges() {
+        let range = |start, end| FieldRange { start, end };
+        assert_eq!(FieldRange::parse("2"), Ok(range(Some(2), Some(2))));
+        assert_eq!(FieldRange::parse("2..3"), Ok(range(Some(2), Some(3))));
+        assert_eq!(FieldRange::parse("-1"), Ok(range(Some(-1), Some(-1))));
+        assert_eq!(FieldRange::parse("..-2"), Ok(range(None, Some(-2))));
+        assert_eq!(FieldRange::parse(".."), Ok(range(None, None)));
+        assert!(FieldRange::parse("0").is_err());
+        assert!(FieldRange::parse("").is_err());
+        assert!(FieldRange::parse("a..b").is_err());
+
+        assert_eq!(range(Some(2), Some(3)).resolve(5), 1..3);
+        assert_eq!(range(Some(-2), None).resolve(5), 3..5);
+        assert_eq!(range(Some(4), None).resolve(2), 0..0);
+        assert_eq!(range(Some(-9), Some(1)).resolve(2), 0..1);
+    }
+
+    #[test]
+    fn test_select() {
+        let fields = Fields::parse("1,-1", Delimiter::parse("\t")).unwrap();
+        let selection = fields.select("7\tnamé\tsrc/a.rs");
+        assert_eq!(selection.text(), "7 src/a.rs");
+
+        let m = Matcher::new(MatcherConfig::default())
+            .match_one(selection.text(), "7s")
+            .unwrap();
+        let m = selection.to_line(m);
+        assert_eq!(m.positions, vec![0, 7]);
+        assert_eq!((m.start, m.end), (0, 8));
+    }
+
+    #[test]
+    fn test_match_fields() {
+        let mut matcher = Matcher::new(MatcherConfig::default());
+        let fields = Fields::parse("2..", Delimiter::whitespace()).unwrap();
+        let m = matcher.match_fields("abc  abc", "abc", &fields).unwrap();
+        assert_eq!(m.positions, vec![5, 6, 7]);
+        assert!(matcher.match_fields("abc", "abc", &fields).is_none());
+    }
+
+    #[cfg(feature = "grapheme")]
+    #[test]
+    fn test_match_fields_graphemes() {
+        let mut matcher = Matcher::new(MatcherConfig {
+            graphemes: true,
+            ..Default::default()
+        });
+        let fields = Fields::parse("1", Delimiter::whitespace()).unwrap();
+        let m = matcher
+            .match_fields("e\u{301}abc\tzzz", "abc", &fields)
+            .unwrap();
+        assert_eq!((m.unit, m.positions), (Unit::Char, vec![2, 3, 4]));
+
+        let m = matcher
+            .match_fields(
+                "zzz e\u{301}x",
+                "ex",
+                &Fields::parse("2", Delimiter::whitespace()).unwrap(),
+            )
+            .unwrap();
+        assert_eq!(m.positions, vec![4, 5, 6]);
+    }
+}
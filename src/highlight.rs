@@ -0,0 +1,188 @@
+//! Rendering matched characters for display.
+//!
+//! [`spans`] divides a text into alternating runs of matched and unmatched
+//! characters, as byte ranges into the original string. The renderers build
+//! on it to produce ANSI-colored or HTML output.
+//!
+//! ```
+//! use rizzer::{fuzzy_find, highlight};
+//!
+//! let text = "src/<main>.rs";
+//! let m = fuzzy_find(text, "main", false, true).unwrap();
+//! assert_eq!(
+//!     highlight::to_html(text, highlight::spans(text, &m)),
+//!     "src/&lt;<mark>main</mark>&gt;.rs"
+//! );
+//! ```
+
+use std::ops::Range;
+
+use crate::text::units;
+use crate::{Match, Unit};
+
+/// Returns the spans of `text` for the positions of `m`.
+///
+/// Every span is a byte range into `text` and whether its characters were
+/// matched. Adjacent matched characters are coalesced into a single span,
+/// as are adjacent unmatched characters, so spans alternate and together
+/// cover the whole text. For a match in grapheme clusters, whole clusters
+/// are matched or unmatched.
+///
+/// # Arguments
+///
+/// * `text` - The original text that was matched.
+/// * `m` - The match.
+pub fn spans<'a>(text: &'a str, m: &'a Match) -> impl Iterator<Item = (Range<usize>, bool)> + 'a {
+    let mut offset = 0;
+    let mut units = units(text, m.unit == Unit::Grapheme)
+        .map(move |unit| {
+            let start = offset;
+            offset += unit.len();
+            start..offset
+        })
+        .enumerate()
+        .peekable();
+    let mut positions = m.positions.iter().copied().peekable();
+
+    std::iter::from_fn(move || {
+        let (index, first) = units.next()?;
+        let is_match = positions.next_if_eq(&index).is_some();
+
+        let mut end = first.end;
+        while let Some((_, unit)) =
+            units.next_if(|(index, _)| (positions.peek() == Some(index)) == is_match)
+        {
+            if is_match {
+                positions.next();
+            }
+            end = unit.end;
+        }
+        Some((first.start..end, is_match))
+    })
+}
+
+/// Renders `text` with ANSI escape codes around the matched spans.
+///
+/// # Arguments
+///
+/// * `text` - The original text.
+/// * `spans` - The spans of `text`, as returned by [`spans`].
+/// * `style` - The SGR parameters of the matched spans, e.g. `"1;32"` for
+///   bold green. The style is reset with `ESC[0m` after every matched span.
+///
+/// # Example
+///
+/// ```
+/// use rizzer::{fuzzy_find, highlight};
+///
+/// let m = fuzzy_find("foobar", "ob", false, true).unwrap();
+/// let ansi = highlight::to_ansi("foobar", highlight::spans("foobar", &m), "1");
+/// assert_eq!(ansi, "fo\x1b[1mob\x1b[0mar");
+/// ```
+pub fn to_ansi(
+    text: &str,
+    spans: impl IntoIterator<Item = (Range<usize>, bool)>,
+    style: &str,
+) -> String {
+    let mut out = String::with_capacity(text.len());
+    for (range, is_match) in spans {
+        if is_match {
+            out.push_str("\x1b[");
+            out.push_str(style);
+            out.push('m');
+            out.push_str(&text[range]);
+            out.push_str("\x1b[0m");
+        } else {
+            out.push_str(&text[range]);
+        }
+    }
+    out
+}
+
+/// Renders `text` as HTML, wrapping the matched spans in `<mark>` elements.
+///
+/// The text is escaped, so the result can be inserted into HTML as is.
+///
+/// # Arguments
+///
+/// * `text` - The original text.
+/// * `spans` - The spans of `text`, as returned by [`spans`].
+pub fn to_html(text: &str, spans: impl IntoIterator<Item = (Range<usize>, bool)>) -> String {
+    let mut out = String::with_capacity(text.len());
+    for (range, is_match) in spans {
+        if is_match {
+            out.push_str("<mark>");
+            escape_html(&text[range], &mut out);
+            out.push_str("</mark>");
+        } else {
+            escape_html(&text[range], &mut out);
+        }
+    }
+    out
+}
+
+/// Appends `text` to `out`, escaping the characters that are special in HTML
+/// text and attribute values.
+fn escape_html(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_positions(positions: Vec<usize>, unit: Unit) -> Match {
+        Match {
+            start: 0,
+            end: 0,
+            score: 0,
+            positions,
+            unit,
+        }
+    }
+
+    #[test]
+    fn test_spans() {
+        let text = "héllo wörld";
+        let m = with_positions(vec![0, 1, 2, 7], Unit::Char);
+        let got: Vec<_> = spans(text, &m).collect();
+        assert_eq!(
+            got,
+            vec![(0..4, true), (4..8, false), (8..10, true), (10..13, false)]
+        );
+        assert_eq!(&text[got[0].0.clone()], "hél");
+        assert_eq!(&text[got[2].0.clone()], "ö");
+
+        let m = with_positions(vec![], Unit::Char);
+        assert_eq!(spans(text, &m).collect::<Vec<_>>(), vec![(0..13, false)]);
+        assert_eq!(spans("", &m).count(), 0);
+    }
+
+    #[test]
+    fn test_to_html_escapes() {
+        let text = "<a href='x'>&</a>";
+        let m = with_positions(vec![1, 12], Unit::Char);
+        assert_eq!(
+            to_html(text, spans(text, &m)),
+            "&lt;<mark>a</mark> href=&#39;x&#39;&gt;<mark>&amp;</mark>&lt;/a&gt;"
+        );
+    }
+
+    #[cfg(feature = "grapheme")]
+    #[test]
+    fn test_grapheme_spans() {
+        let text = "e\u{301}x🇩🇪";
+        let m = with_positions(vec![0, 2], Unit::Grapheme);
+        let got: Vec<_> = spans(text, &m).collect();
+        assert_eq!(got, vec![(0..3, true), (3..4, false), (4..12, true)]);
+    }
+}
@@ -0,0 +1,346 @@
+//! Matching structured items by several weighted fields.
+//!
+//! Each [`Key`] extracts one text from an item and has a weight, by which the
+//! score of a match in that text is multiplied. [`Keys`] combines the scores
+//! of all keys into the score of the item.
+//!
+//! ```
+//! use rizzer::keys::{Combine, Key, Keys};
+//! use rizzer::{Matcher, MatcherConfig};
+//!
+//! struct User {
+//!     name: String,
+//!     email: String,
+//! }
+//!
+//! let keys = Keys::new(
+//!     vec![
+//!         Key::new(2, |u: &User| u.name.as_str().into()),
+//!         Key::new(1, |u: &User| u.email.as_str().into()),
+//!     ],
+//!     Combine::Max,
+//! );
+//! let user = User {
+//!     name: "Ada Lovelace".to_string(),
+//!     email: "ada@example.com".to_string(),
+//! };
+//!
+//! let mut matcher = Matcher::new(MatcherConfig::default());
+//! let m = matcher.match_keys(&user, "love", &keys).unwrap();
+//! assert_eq!(m.best, 0);
+//! assert_eq!(m.fields[0].1.positions, vec![4, 5, 6, 7]);
+//! ```
+
+use std::borrow::Cow;
+use std::fmt;
+
+use crate::{Match, Matcher};
+
+/// Returns the text of a field of an item.
+type Extract<T> = dyn for<'a> Fn(&'a T) -> Cow<'a, str> + Send + Sync;
+
+/// A field of an item to match against, with a weight.
+pub struct Key<T> {
+    weight: i32,
+    extract: Box<Extract<T>>,
+}
+
+impl<T> Key<T> {
+    /// Creates a key.
+    ///
+    /// # Arguments
+    ///
+    /// * `weight` - The factor by which the score of a match in this field
+    ///   is multiplied.
+    /// * `extract` - Returns the text of the field of an item. A field with
+    ///   several values, such as a list of tags, can be joined into an owned
+    ///   string.
+    pub fn new<F>(weight: i32, extract: F) -> Self
+    where
+        F: for<'a> Fn(&'a T) -> Cow<'a, str> + Send + Sync + 'static,
+    {
+        Key {
+            weight,
+            extract: Box::new(extract),
+        }
+    }
+
+    /// Returns the weight of this key.
+    pub fn weight(&self) -> i32 {
+        self.weight
+    }
+
+    /// Returns the text of this field of `item`.
+    pub fn text<'a>(&self, item: &'a T) -> Cow<'a, str> {
+        (self.extract)(item)
+    }
+}
+
+impl<T> fmt::Debug for Key<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Key")
+            .field("weight", &self.weight)
+            .finish_non_exhaustive()
+    }
+}
+
+/// How the weighted scores of the fields of an item are combined.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Combine {
+    /// The score of the item is the highest weighted score of any field.
+    #[default]
+    Max,
+    /// The score of the item is the sum of the weighted scores of all
+    /// matching fields, favoring items that match in several fields.
+    Sum,
+    /// The score of the item is the highest weighted score of any field,
+    /// plus `tie_breaker_percent` percent of the weighted scores of the other
+    /// matching fields. With 0 this is `Max`, with 100 it is `Sum`.
+    BestField {
+        /// The percentage of the other fields' scores that is added.
+        tie_breaker_percent: i32,
+    },
+}
+
+/// The keys of an item and how their scores are combined.
+#[derive(Debug)]
+pub struct Keys<T> {
+    keys: Vec<Key<T>>,
+    combine: Combine,
+}
+
+impl<T> Keys<T> {
+    /// Creates a set of keys.
+    pub fn new(keys: Vec<Key<T>>, combine: Combine) -> Self {
+        Keys { keys, combine }
+    }
+
+    /// Returns the keys.
+    pub fn keys(&self) -> &[Key<T>] {
+        &self.keys
+    }
+
+    /// Combines the matches of the fields of an item into its score.
+    ///
+    /// # Returns
+    ///
+    /// The score of the item and the index of the key with the highest
+    /// weighted score, or `None` if no field matched.
+    ///
+    /// Scores are combined in `i64`, so large weights cannot overflow, and
+    /// the result saturates at the bounds of `i32`.
+    fn combine(&self, fields: &[(usize, Match)]) -> Option<(i32, usize)> {
+        let weighted = |(key, m): &(usize, Match)| {
+            (i64::from(m.score) * i64::from(self.keys[*key].weight), *key)
+        };
+        // The first of several equally good fields wins.
+        let (best, best_key) = fields
+            .iter()
+            .map(weighted)
+            .reduce(|a, b| if b.0 > a.0 { b } else { a })?;
+        let total: i64 = fields.iter().map(|f| weighted(f).0).sum();
+
+        let score = match self.combine {
+            Combine::Max => best,
+            Combine::Sum => total,
+            Combine::BestField {
+                tie_breaker_percent,
+            } => best + (total - best) * i64::from(tie_breaker_percent) / 100,
+        };
+        let score = score.clamp(i64::from(i32::MIN), i64::from(i32::MAX)) as i32;
+        Some((score, best_key))
+    }
+}
+
+/// The result of matching an item by its keys.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeysMatch {
+    /// The combined score of the item. Higher is better.
+    pub score: i32,
+    /// The index of the key whose field had the highest weighted score.
+    pub best: usize,
+    /// The index of the key and the match of every field that matched, in
+    /// the order of the keys. Positions refer to the text extracted by the
+    /// key, and scores are not weighted.
+    pub fields: Vec<(usize, Match)>,
+}
+
+impl Matcher {
+    /// Matches `pattern` against every field of `item`.
+    ///
+    /// # Arguments
+    ///
+    /// * `item` - The item to search in.
+    /// * `pattern` - The pattern to search for.
+    /// * `keys` - The fields of the item and how to combine their scores.
+    ///
+    /// # Returns
+    ///
+    /// `Some(KeysMatch)` if `pattern` matches at least one field, `None`
+    /// otherwise.
+    pub fn match_keys<T>(&mut self, item: &T, pattern: &str, keys: &Keys<T>) -> Option<KeysMatch> {
+        self.set_pattern(pattern);
+        let track_positions = self.config().track_positions;
+        let fields: Vec<(usize, Match)> = keys
+            .keys
+            .iter()
+            .enumerate()
+            .filter_map(|(i, key)| {
+                self.match_text(&key.text(item), track_positions)
+                    .map(|m| (i, m))
+            })
+            .collect();
+
+        let (score, best) = keys.combine(&fields)?;
+        Some(KeysMatch {
+            score,
+            best,
+            fields,
+        })
+    }
+
+    /// Matches `pattern` against every item and ranks the hits.
+    ///
+    /// # Arguments
+    ///
+    /// * `items` - The items to search in.
+    /// * `pattern` - The pattern to search for.
+    /// * `keys` - The fields of the items and how to combine their scores.
+    ///
+    /// # Returns
+    ///
+    /// The index and match of every item that matched, by descending score.
+    /// Items with equal scores keep the input order.
+    pub fn match_all_keys<T>(
+        &mut self,
+        items: &[T],
+        pattern: &str,
+        keys: &Keys<T>,
+    ) -> Vec<(usize, KeysMatch)> {
+        let mut matches: Vec<(usize, KeysMatch)> = items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, item)| self.match_keys(item, pattern, keys).map(|m| (i, m)))
+            .collect();
+        matches.sort_by_key(|(_, m)| std::cmp::Reverse(m.score));
+        matches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MatcherConfig;
+
+    struct User {
+        name: &'static str,
+        email: &'static str,
+        tags: Vec<&'static str>,
+    }
+
+    fn keys(combine: Combine) -> Keys<User> {
+        Keys::new(
+            vec![
+                Key::new(2, |u: &User| u.name.into()),
+                Key::new(1, |u: &User| u.email.into()),
+                Key::new(1, |u: &User| u.tags.join(" ").into()),
+            ],
+            combine,
+        )
+    }
+
+    fn users() -> Vec<User> {
+        vec![
+            User {
+                name: "Grace Hopper",
+                email: "grace@navy.mil",
+                tags: vec!["cobol", "compilers"],
+            },
+            User {
+                name: "Ada Lovelace",
+                email: "countess@analytical.engine",
+                tags: vec!["math", "ada"],
+            },
+            User {
+                name: "Alan Turing",
+                email: "alan@bletchley.uk",
+                tags: vec!["math"],
+            },
+        ]
+    }
+
+    #[test]
+    fn test_match_keys() {
+        let mut matcher = Matcher::new(MatcherConfig::default());
+        let users = users();
+
+        let m = matcher
+            .match_keys(&users[1], "ada", &keys(Combine::Max))
+            .unwrap();
+        let name = matcher.score_one("Ada Lovelace", "ada").unwrap();
+        assert_eq!(m.best, 0);
+        assert_eq!(m.score, name * 2);
+        let matched: Vec<usize> = m.fields.iter().map(|(key, _)| *key).collect();
+        assert_eq!(matched, vec![0, 2]);
+        assert_eq!(m.fields[1].1.positions, vec![5, 6, 7]);
+
+        assert!(matcher
+            .match_keys(&users[0], "xyz", &keys(Combine::Max))
+            .is_none());
+    }
+
+    #[test]
+    fn test_combine() {
+        let mut matcher = Matcher::new(MatcherConfig::default());
+        let users = users();
+        let mut score = |combine| {
+            matcher
+                .match_keys(&users[1], "ada", &keys(combine))
+                .unwrap()
+                .score
+        };
+
+        let max = score(Combine::Max);
+        let sum = score(Combine::Sum);
+        assert!(sum > max);
+        assert_eq!(
+            score(Combine::BestField {
+                tie_breaker_percent: 0
+            }),
+            max
+        );
+        assert_eq!(
+            score(Combine::BestField {
+                tie_breaker_percent: 100
+            }),
+            sum
+        );
+        let half = score(Combine::BestField {
+            tie_breaker_percent: 50,
+        });
+        assert!(max < half && half < sum);
+    }
+
+    #[test]
+    fn test_large_weights() {
+        let mut matcher = Matcher::new(MatcherConfig::default());
+        let keys = Keys::new(
+            vec![
+                Key::new(i32::MAX, |u: &User| u.name.into()),
+                Key::new(1_000_000, |u: &User| u.tags.join(" ").into()),
+            ],
+            Combine::Sum,
+        );
+        let m = matcher.match_keys(&users()[1], "ada", &keys).unwrap();
+        assert_eq!((m.score, m.best), (i32::MAX, 0));
+    }
+
+    #[test]
+    fn test_match_all_keys() {
+        let mut matcher = Matcher::new(MatcherConfig::default());
+        let hits = matcher.match_all_keys(&users(), "math", &keys(Combine::Sum));
+        let order: Vec<usize> = hits.iter().map(|(i, _)| *i).collect();
+        assert_eq!(order, vec![1, 2]);
+        assert!(hits.iter().all(|(_, m)| m.best == 2));
+    }
+}
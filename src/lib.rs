@@ -2,74 +2,191 @@
 //!
 //! This library provides functions for fuzzy matching between a text and a pattern,
 //! with options for case sensitivity and Unicode normalization.
+//!
+//! For matching a pattern against many candidates, use a [`Matcher`], which
+//! reuses its buffers between calls.
 
-use unicode_normalization::UnicodeNormalization;
+mod batch;
+pub mod fields;
+pub mod highlight;
+pub mod keys;
+mod matcher;
+mod prefilter;
+pub mod query;
+mod rank;
+mod scoring;
+mod session;
+mod text;
 
-// Constants
-const SCORE_MATCH: i32 = 16;
-const SCORE_GAP_START: i32 = -3;
-const SCORE_GAP_EXTENSION: i32 = -1;
-const BONUS_BOUNDARY: i32 = SCORE_MATCH / 2;
-const BONUS_FIRST_CHAR_MULTIPLIER: i32 = 2;
+pub use matcher::{Algorithm, CaseMatching, Matcher, MatcherConfig, PathMode};
+pub use query::Query;
+pub use rank::{RankKey, Tiebreak};
+pub use scoring::Scoring;
+pub use session::Session;
+pub use text::Normalization;
 
-/// Normalizes a Unicode character.
-///
-/// # Arguments
+use std::ops::Range;
+
+use text::units;
+
+/// The result of a successful fuzzy match.
 ///
-/// * `r` - The character to normalize.
+/// Positions are indices of characters (`char`s) in the original text, before
+/// any case folding or normalization, or of grapheme clusters if `unit` says
+/// so. Use [`Match::positions_in`] and [`Match::byte_ranges`] to express them
+/// in other units.
 ///
-/// # Returns
-///
-/// The normalized character, or the original character if normalization fails.
-fn normalize_rune(r: char) -> char {
-    r.to_lowercase().nfd().next().unwrap_or(r)
+/// Matches are ordered by quality, the better match being greater: a higher
+/// score, then an earlier start, then an earlier end. `max` thus returns the
+/// best match, and sorting in reverse puts it first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match {
+    /// Index of the first matched character in the text.
+    pub start: usize,
+    /// Index one past the last matched character in the text.
+    pub end: usize,
+    /// Score of the match. Higher is better.
+    pub score: i32,
+    /// Indices of the matched characters in the text, in ascending order.
+    pub positions: Vec<usize>,
+    /// The unit that `start`, `end` and `positions` count.
+    pub unit: Unit,
 }
 
-/// Represents the character class for bonus calculation.
-enum CharClass {
-    White,
-    Alnum,
-    Punct,
+/// The unit that the positions of a [`Match`] count.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Unit {
+    /// Characters (`char`s) of the original text.
+    #[default]
+    Char,
+    /// Extended grapheme clusters of the original text, for matches made with
+    /// `MatcherConfig::graphemes` and the `grapheme` cargo feature.
+    Grapheme,
 }
 
-/// Determines the character class of a given character.
-///
-/// # Arguments
-///
-/// * `c` - The character to classify.
-///
-/// # Returns
+/// The unit in which an offset into a string is expressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Offset {
+    /// Byte offset into the UTF-8 string, suitable for slicing a `&str`.
+    Byte,
+    /// Index of the `char` (Unicode scalar value).
+    Char,
+    /// Offset in UTF-16 code units, as used by LSP and JavaScript strings.
+    Utf16,
+}
+
+impl Match {
+    /// Returns the matched positions expressed in `unit`.
+    ///
+    /// For a match in grapheme clusters, every position is the offset of the
+    /// start of its cluster.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The original text that was matched.
+    /// * `unit` - The unit to express the positions in.
+    ///
+    /// # Returns
+    ///
+    /// The offset of every matched character, in ascending order.
+    pub fn positions_in(&self, text: &str, unit: Offset) -> Vec<usize> {
+        convert_offsets(text, &self.positions, self.unit, unit)
+    }
+
+    /// Returns the span of the match, from `start` to `end`, expressed in `unit`.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The original text that was matched.
+    /// * `unit` - The unit to express the span in.
+    pub fn range_in(&self, text: &str, unit: Offset) -> Range<usize> {
+        let offsets = convert_offsets(text, &[self.start, self.end], self.unit, unit);
+        offsets[0]..offsets[1]
+    }
+
+    /// Returns the byte range of every matched character, or of every matched
+    /// grapheme cluster for a match in grapheme clusters.
+    ///
+    /// The ranges can be used to slice `text` directly, e.g. for highlighting.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The original text that was matched.
+    pub fn byte_ranges(&self, text: &str) -> Vec<Range<usize>> {
+        let mut positions = self.positions.iter().peekable();
+        let mut start = 0;
+        units(text, self.unit == Unit::Grapheme)
+            .enumerate()
+            .filter_map(|(index, unit)| {
+                let range = start..start + unit.len();
+                start = range.end;
+                positions.next_if_eq(&&index).map(|_| range)
+            })
+            .collect()
+    }
+}
+
+/// Converts ascending indices of units `from` into offsets in `to`.
 ///
-/// The `CharClass` of the input character.
-fn char_class(c: char) -> CharClass {
-    if c.is_whitespace() {
-        CharClass::White
-    } else if c.is_alphanumeric() {
-        CharClass::Alnum
-    } else {
-        CharClass::Punct
+/// An index equal to the number of units in `text` is converted to the end of
+/// `text`.
+fn convert_offsets(text: &str, indices: &[usize], from: Unit, to: Offset) -> Vec<usize> {
+    if from == Unit::Char && to == Offset::Char {
+        return indices.to_vec();
     }
+
+    let mut offsets = Vec::with_capacity(indices.len());
+    let mut units = units(text, from == Unit::Grapheme);
+    let (mut index, mut byte, mut char, mut utf16) = (0, 0, 0, 0);
+    for &target in indices {
+        while index < target {
+            match units.next() {
+                Some(unit) => {
+                    index += 1;
+                    byte += unit.len();
+                    char += unit.chars().count();
+                    utf16 += unit.encode_utf16().count();
+                }
+                None => break,
+            }
+        }
+        offsets.push(match to {
+            Offset::Byte => byte,
+            Offset::Utf16 => utf16,
+            Offset::Char => char,
+        });
+    }
+    offsets
 }
 
-/// Calculates the bonus score based on the previous and current character classes.
+/// Performs a fuzzy match between `text` and `pattern`.
 ///
 /// # Arguments
 ///
-/// * `prev_class` - The `CharClass` of the previous character.
-/// * `curr_class` - The `CharClass` of the current character.
+/// * `text` - The text to search in.
+/// * `pattern` - The pattern to search for.
+/// * `case_sensitive` - Whether the match should be case-sensitive.
+/// * `normalize` - Whether to apply Unicode normalization.
 ///
 /// # Returns
 ///
-/// The calculated bonus score as an `i32`.
-fn bonus_for(prev_class: &CharClass, curr_class: &CharClass) -> i32 {
-    match curr_class {
-        CharClass::Alnum => match prev_class {
-            CharClass::White => BONUS_BOUNDARY + 2,
-            CharClass::Punct => BONUS_BOUNDARY + 1,
-            CharClass::Alnum => 0,
-        },
-        _ => 0,
-    }
+/// `Some(Match)` if `pattern` matches `text`, `None` otherwise.
+///
+/// Every character of `pattern` must be matched, in order. An empty pattern
+/// trivially matches any text and yields a `Match` with a score of 0 and no
+/// positions.
+pub fn fuzzy_find(
+    text: &str,
+    pattern: &str,
+    case_sensitive: bool,
+    normalize: bool,
+) -> Option<Match> {
+    Matcher::new(MatcherConfig {
+        case_matching: case_sensitive.into(),
+        normalization: normalize.into(),
+        ..Default::default()
+    })
+    .match_one(text, pattern)
 }
 
 /// Performs a fuzzy match between `text` and `pattern`.
@@ -90,120 +207,32 @@ fn bonus_for(prev_class: &CharClass, curr_class: &CharClass) -> i32 {
 /// - vector of matched positions in `text` (Vec<usize>)
 ///
 /// If no match is found, returns (-1, -1, 0, vec![]).
+///
+/// Unlike `fuzzy_find`, this keeps the behavior of earlier versions, in which
+/// pattern characters may be skipped: `"abc"` matches `"axx"` at position 0.
+#[deprecated(
+    since = "0.3.0",
+    note = "use `fuzzy_find`, which returns `Option<Match>`"
+)]
 pub fn fuzzy_match(
     text: &str,
     pattern: &str,
     case_sensitive: bool,
     normalize: bool,
 ) -> (isize, isize, i32, Vec<usize>) {
-    if pattern.is_empty() {
-        return (0, 0, 0, vec![]);
+    let m = Matcher::new(MatcherConfig {
+        case_matching: case_sensitive.into(),
+        normalization: normalize.into(),
+        ..Default::default()
+    })
+    .match_partial(text, pattern);
+    match m {
+        Some(m) => (m.start as isize, m.end as isize, m.score, m.positions),
+        None => (-1, -1, 0, vec![]),
     }
-
-    let text = if !case_sensitive {
-        text.to_lowercase()
-    } else {
-        text.to_string()
-    };
-    let pattern = if !case_sensitive {
-        pattern.to_lowercase()
-    } else {
-        pattern.to_string()
-    };
-
-    let text: String = if normalize {
-        text.chars().map(normalize_rune).collect()
-    } else {
-        text
-    };
-    let pattern: String = if normalize {
-        pattern.chars().map(normalize_rune).collect()
-    } else {
-        pattern
-    };
-
-    let (m, n) = (pattern.len(), text.len());
-
-    if m > n {
-        return (-1, -1, 0, vec![]);
-    }
-
-    // Phase 1 & 2: Bonus calculation
-    let mut bonus = vec![0; n];
-    let mut prev_class = CharClass::White;
-    for (i, c) in text.chars().enumerate() {
-        let curr_class = char_class(c);
-        bonus[i] = bonus_for(&prev_class, &curr_class);
-        prev_class = curr_class;
-    }
-
-    // Phase 3: Score matrix calculation
-    let mut h = vec![vec![0; n + 1]; m + 1];
-    for i in 1..=m {
-        h[i][0] = SCORE_GAP_START + (i as i32 - 1) * SCORE_GAP_EXTENSION;
-    }
-
-    let (mut max_score, mut max_i, mut max_j) = (0, 0, 0);
-
-    let pattern: Vec<char> = pattern.chars().collect();
-    let text: Vec<char> = text.chars().collect();
-
-    for i in 1..=m {
-        for j in 1..=n {
-            let score = if pattern[i - 1] == text[j - 1] {
-                let mut score = h[i - 1][j - 1] + SCORE_MATCH;
-                if i == 1 {
-                    score += bonus[j - 1] * BONUS_FIRST_CHAR_MULTIPLIER;
-                } else {
-                    score += bonus[j - 1];
-                }
-                score
-            } else {
-                std::cmp::max(
-                    h[i][j - 1] + SCORE_GAP_EXTENSION,
-                    h[i - 1][j] + SCORE_GAP_START,
-                )
-            };
-
-            h[i][j] = std::cmp::max(0, score);
-
-            if h[i][j] > max_score {
-                max_score = h[i][j];
-                max_i = i;
-                max_j = j;
-            }
-        }
-    }
-
-    if max_score == 0 {
-        return (-1, -1, 0, vec![]);
-    }
-
-    // Phase 4: Backtracing
-    let mut positions = Vec::new();
-    let (mut i, mut j) = (max_i, max_j);
-    while i > 0 && j > 0 {
-        if pattern[i - 1] == text[j - 1] {
-            positions.push(j - 1);
-            i -= 1;
-            j -= 1;
-        } else if h[i][j - 1] + SCORE_GAP_EXTENSION == h[i][j] {
-            j -= 1;
-        } else {
-            i -= 1;
-        }
-    }
-    positions.reverse();
-
-    (
-        positions[0] as isize,
-        (positions[positions.len() - 1] + 1) as isize,
-        max_score,
-        positions,
-    )
 }
 
-/// This is a simplified version of `fuzzy_match` that only returns the match score.
+/// This is a simplified version of `fuzzy_find` that only returns the match score.
 ///
 /// # Arguments
 ///
@@ -214,10 +243,15 @@ pub fn fuzzy_match(
 ///
 /// # Returns
 ///
-/// The match score as an `i32`.
+/// The match score as an `i32`, or 0 if there is no match.
 pub fn fuzzy_match_score(text: &str, pattern: &str, case_sensitive: bool, normalize: bool) -> i32 {
-    let (_, _, score, _) = fuzzy_match(text, pattern, case_sensitive, normalize);
-    score
+    Matcher::new(MatcherConfig {
+        case_matching: case_sensitive.into(),
+        normalization: normalize.into(),
+        ..Default::default()
+    })
+    .score_one(text, pattern)
+    .unwrap_or(0)
 }
 
 #[cfg(test)]
@@ -225,6 +259,7 @@ mod tests {
     use super::*;
 
     #[test]
+    #[allow(deprecated)]
     fn test_fuzzy_match_v2() {
         let text = "abcdefghijklmnopqrstuvwxyz";
         let pattern = "ace";
@@ -241,5 +276,85 @@ mod tests {
         let pattern = "alm";
         let score = fuzzy_match_score(text, pattern, false, true);
         assert!(score > 0);
+
+        // Long texts fall back to the greedy algorithm, whose scores are
+        // clamped like those of the score matrix.
+        let long = format!("a{}b", "x".repeat(200_000));
+        assert!(fuzzy_match_score(&long, "ab", false, true) > 0);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_fuzzy_match_skips_pattern_characters() {
+        let (start, end, score, positions) = fuzzy_match("axx", "abc", false, true);
+        assert_eq!((start, end, positions), (0, 1, vec![0]));
+        assert!(score > 0);
+        assert_eq!(fuzzy_find("axx", "abc", false, true), None);
+    }
+
+    #[test]
+    fn test_fuzzy_find() {
+        let m = fuzzy_find("abcdefghijklmnopqrstuvwxyz", "ace", false, true).unwrap();
+        assert_eq!(m.start, 0);
+        assert_eq!(m.end, 5);
+        assert!(m.score > 0);
+        assert_eq!(m.positions, vec![0, 2, 4]);
+
+        assert_eq!(fuzzy_find("abc", "xyz", false, true), None);
+    }
+
+    #[test]
+    fn test_fuzzy_find_empty_pattern() {
+        let m = fuzzy_find("abc", "", false, true).unwrap();
+        assert_eq!(m.score, 0);
+        assert!(m.positions.is_empty());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_fuzzy_match_no_match_sentinel() {
+        assert_eq!(fuzzy_match("abc", "xyz", false, true), (-1, -1, 0, vec![]));
+    }
+
+    #[test]
+    fn test_positions_in() {
+        let text = "İstanbul 🦀 rust";
+        let m = fuzzy_find(text, "irust", false, true).unwrap();
+        assert_eq!(m.positions, vec![0, 11, 12, 13, 14]);
+        assert_eq!(m.positions_in(text, Offset::Char), m.positions);
+        assert_eq!(m.positions_in(text, Offset::Byte), vec![0, 15, 16, 17, 18]);
+        assert_eq!(m.positions_in(text, Offset::Utf16), vec![0, 12, 13, 14, 15]);
+        assert_eq!(m.range_in(text, Offset::Byte), 0..19);
+        assert_eq!(m.range_in(text, Offset::Utf16), 0..16);
+
+        let ranges = m.byte_ranges(text);
+        assert_eq!(&text[ranges[0].clone()], "İ");
+        assert_eq!(&text[ranges[1].clone()], "r");
+    }
+
+    #[cfg(feature = "grapheme")]
+    #[test]
+    fn test_positions_in_graphemes() {
+        let text = "e\u{301}abc";
+        let m = Matcher::new(MatcherConfig {
+            graphemes: true,
+            ..Default::default()
+        })
+        .match_one(text, "abc")
+        .unwrap();
+        assert_eq!(
+            (m.unit, m.positions.clone()),
+            (Unit::Grapheme, vec![1, 2, 3])
+        );
+        assert_eq!(m.positions_in(text, Offset::Char), vec![2, 3, 4]);
+        assert_eq!(m.positions_in(text, Offset::Utf16), vec![2, 3, 4]);
+        assert_eq!(m.range_in(text, Offset::Byte), 3..6);
+        assert_eq!(m.byte_ranges(text), vec![3..4, 4..5, 5..6]);
+    }
+
+    #[test]
+    fn test_fuzzy_find_case_sensitive_normalized() {
+        assert!(fuzzy_find("Crème", "Creme", true, true).is_some());
+        assert!(fuzzy_find("CRÈME", "creme", true, true).is_none());
     }
 }
@@ -0,0 +1,1520 @@
+//! A reusable matcher that owns its scratch buffers.
+
+use crate::prefilter::{is_subsequence, is_subsequence_ascii};
+use crate::query::{Query, Term, TermKind};
+use crate::rank::{RankKey, Tiebreak};
+use crate::scoring::Scoring;
+use crate::text::{fold_pattern, IndexedText, Normalization};
+use crate::{Match, Unit};
+
+/// How the case of characters is taken into account when matching.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CaseMatching {
+    /// Matching is case-sensitive.
+    Respect,
+    /// Matching is case-insensitive.
+    #[default]
+    Ignore,
+    /// Matching is case-insensitive unless the pattern contains an uppercase
+    /// character, following the fzf and ripgrep convention.
+    Smart,
+}
+
+impl CaseMatching {
+    /// Determines whether matching `pattern` should be case-sensitive.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - The pattern to search for.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the match should be case-sensitive.
+    pub fn is_case_sensitive(self, pattern: &str) -> bool {
+        match self {
+            CaseMatching::Respect => true,
+            CaseMatching::Ignore => false,
+            CaseMatching::Smart => pattern.chars().any(char::is_uppercase),
+        }
+    }
+}
+
+impl From<bool> for CaseMatching {
+    /// Converts a `case_sensitive` flag into `Respect` or `Ignore`.
+    fn from(case_sensitive: bool) -> Self {
+        if case_sensitive {
+            CaseMatching::Respect
+        } else {
+            CaseMatching::Ignore
+        }
+    }
+}
+
+/// Whether texts are matched as file paths.
+///
+/// In path mode, path separators are the only delimiters, the start of a path
+/// counts as a word boundary, and characters in the final path component earn
+/// `Scoring::bonus_basename`. Combine it with [`Scoring::path`].
+///
+/// Shorter paths are preferred among equal scores by the default
+/// [`Tiebreak::Length`] criterion, which applies in every mode.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PathMode {
+    /// Texts are not treated as paths.
+    #[default]
+    Off,
+    /// Paths are separated by `/`.
+    Unix,
+    /// Paths are separated by `/` or `\`.
+    Windows,
+}
+
+impl PathMode {
+    /// Returns the path separators, or `None` if path mode is off.
+    pub(crate) fn separators(self) -> Option<&'static [char]> {
+        match self {
+            PathMode::Off => None,
+            PathMode::Unix => Some(&['/']),
+            PathMode::Windows => Some(&['/', '\\']),
+        }
+    }
+}
+
+/// The algorithm used to fuzzy match a pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    /// Finds the alignment with the highest score, in O(m·n) time for a
+    /// pattern of length m and a text of length n.
+    Optimal,
+    /// Scores the shortest window around the first occurrence of the pattern,
+    /// in O(n) time. Faster, but may miss a better alignment.
+    Greedy,
+    /// Uses `Optimal`, unless the score matrix would have more than
+    /// `max_cells` cells, in which case `Greedy` is used.
+    Auto {
+        /// The largest score matrix `Optimal` is used for.
+        max_cells: usize,
+    },
+}
+
+impl Default for Algorithm {
+    fn default() -> Self {
+        Algorithm::Auto {
+            max_cells: 100 * 1024,
+        }
+    }
+}
+
+/// Configuration for a [`Matcher`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatcherConfig {
+    /// How the case of characters is taken into account.
+    pub case_matching: CaseMatching,
+    /// How thoroughly characters are folded before matching.
+    pub normalization: Normalization,
+    /// The scores and bonuses used to rank matches.
+    pub scoring: Scoring,
+    /// Whether texts are matched as file paths.
+    pub path_mode: PathMode,
+    /// The algorithm used to fuzzy match a pattern.
+    pub algorithm: Algorithm,
+    /// Whether to compute the matched positions. When disabled, only two
+    /// rows of the score matrix are kept and the backtracing phase is
+    /// skipped, so `Match::positions` is left empty and `Match::start` and
+    /// `Match::end` are set to 0.
+    pub track_positions: bool,
+    /// The criteria by which [`Matcher::sort_matches`] ranks matches, in
+    /// order. Remaining ties are broken by the index of the candidate.
+    /// Criteria that look at the matched positions need `track_positions`.
+    pub tiebreak: Vec<Tiebreak>,
+    /// Whether to report positions in extended grapheme clusters instead of
+    /// characters. A flag emoji or an 'e' followed by a combining accent is
+    /// then a single unit, and `Match::positions`, `Match::start` and
+    /// `Match::end` are indices of clusters in the original text, with
+    /// `Match::unit` set to `Unit::Grapheme`.
+    ///
+    /// Matching still compares characters, so a cluster counts as matched
+    /// when any of its characters is: with `Normalization::None`, "e" matches
+    /// the cluster "e\u{301}".
+    ///
+    /// This needs the `grapheme` cargo feature. Without it, the flag is
+    /// ignored and positions are indices of characters.
+    pub graphemes: bool,
+}
+
+impl MatcherConfig {
+    /// Returns `true` if positions are indices of grapheme clusters.
+    pub(crate) fn graphemes(&self) -> bool {
+        cfg!(feature = "grapheme") && self.graphemes
+    }
+
+    /// Returns the unit that positions count.
+    pub(crate) fn unit(&self) -> Unit {
+        if self.graphemes() {
+            Unit::Grapheme
+        } else {
+            Unit::Char
+        }
+    }
+}
+
+impl Default for MatcherConfig {
+    fn default() -> Self {
+        MatcherConfig {
+            case_matching: CaseMatching::default(),
+            normalization: Normalization::default(),
+            scoring: Scoring::default(),
+            path_mode: PathMode::default(),
+            algorithm: Algorithm::default(),
+            track_positions: true,
+            tiebreak: Tiebreak::DEFAULT.to_vec(),
+            graphemes: false,
+        }
+    }
+}
+
+/// A fuzzy matcher that can be reused across many calls.
+///
+/// The matcher keeps its pattern, text, bonus and score buffers between calls,
+/// so matching a pattern against many candidates does not allocate per item
+/// once the buffers have grown to fit the longest candidate.
+///
+/// # Example
+///
+/// ```
+/// use rizzer::{Matcher, MatcherConfig};
+///
+/// let mut matcher = Matcher::new(MatcherConfig::default());
+/// let m = matcher.match_one("abcdefghijklmnopqrstuvwxyz", "ace").unwrap();
+/// assert_eq!(m.positions, vec![0, 2, 4]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Matcher {
+    config: MatcherConfig,
+    case_sensitive: bool,
+    pattern: Vec<char>,
+    /// The prepared pattern as bytes, if it is ASCII.
+    pattern_ascii: Option<Vec<u8>>,
+    text: IndexedText,
+    first: Vec<usize>,
+    h: Vec<i32>,
+    c: Vec<u32>,
+}
+
+impl Matcher {
+    /// Creates a new matcher with the given configuration.
+    pub fn new(config: MatcherConfig) -> Self {
+        Matcher {
+            config,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the configuration of this matcher.
+    pub fn config(&self) -> &MatcherConfig {
+        &self.config
+    }
+
+    /// Performs a fuzzy match between `text` and `pattern`.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The text to search in.
+    /// * `pattern` - The pattern to search for.
+    ///
+    /// # Returns
+    ///
+    /// `Some(Match)` if `pattern` matches `text`, `None` otherwise.
+    pub fn match_one(&mut self, text: &str, pattern: &str) -> Option<Match> {
+        self.set_pattern(pattern);
+        self.match_text(text, self.config.track_positions)
+    }
+
+    /// Returns only the score of a fuzzy match between `text` and `pattern`.
+    ///
+    /// Positions are never computed, regardless of `track_positions`. Only
+    /// two rows of the score matrix are kept, so this uses memory linear in
+    /// the length of `text`.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The text to search in.
+    /// * `pattern` - The pattern to search for.
+    ///
+    /// # Returns
+    ///
+    /// `Some(score)` if `pattern` matches `text`, `None` otherwise.
+    pub fn score_one(&mut self, text: &str, pattern: &str) -> Option<i32> {
+        self.set_pattern(pattern);
+        self.score_text(text)
+    }
+
+    /// Matches `pattern` against every candidate.
+    ///
+    /// The pattern is prepared once and the buffers are shared across all
+    /// candidates.
+    ///
+    /// # Arguments
+    ///
+    /// * `candidates` - The texts to search in.
+    /// * `pattern` - The pattern to search for.
+    ///
+    /// # Returns
+    ///
+    /// The index and match of every candidate that matched, in input order.
+    pub fn match_many<S: AsRef<str>>(
+        &mut self,
+        candidates: &[S],
+        pattern: &str,
+    ) -> Vec<(usize, Match)> {
+        self.set_pattern(pattern);
+        let track_positions = self.config.track_positions;
+        candidates
+            .iter()
+            .enumerate()
+            .filter_map(|(i, text)| {
+                self.match_text(text.as_ref(), track_positions)
+                    .map(|m| (i, m))
+            })
+            .collect()
+    }
+
+    /// Sorts matches from best to worst.
+    ///
+    /// Matches are ranked by the `tiebreak` chain of the configuration, which
+    /// by default prefers higher scores and then shorter candidates.
+    /// Remaining ties keep the candidate order, so the result is the same
+    /// regardless of the order of `matches`.
+    ///
+    /// # Arguments
+    ///
+    /// * `candidates` - The texts that were matched.
+    /// * `matches` - The index and match of every candidate that matched, as
+    ///   returned by [`Matcher::match_many`].
+    pub fn sort_matches<S: AsRef<str>>(&self, candidates: &[S], matches: &mut [(usize, Match)]) {
+        matches.sort_by_cached_key(|(i, m)| self.rank_key(candidates[*i].as_ref(), *i, m));
+    }
+
+    /// Returns the key by which [`Matcher::sort_matches`] orders a match,
+    /// smaller keys being better.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The candidate that matched.
+    /// * `index` - The index of the candidate.
+    /// * `m` - The match.
+    pub fn rank_key(&self, text: &str, index: usize, m: &Match) -> RankKey {
+        let located = (!m.positions.is_empty()).then_some(m);
+        RankKey::from_score(
+            &self.config.tiebreak,
+            text,
+            index,
+            m.score,
+            located,
+            self.config.graphemes(),
+        )
+    }
+
+    /// Prepares `pattern` for matching according to the configuration.
+    pub(crate) fn set_pattern(&mut self, pattern: &str) {
+        self.case_sensitive = self.config.case_matching.is_case_sensitive(pattern);
+        fold_pattern(
+            pattern,
+            self.case_sensitive,
+            self.config.normalization,
+            &mut self.pattern,
+        );
+
+        let mut bytes = self.pattern_ascii.take().unwrap_or_default();
+        bytes.clear();
+        if self.pattern.iter().all(char::is_ascii) {
+            bytes.extend(self.pattern.iter().map(|&c| c as u8));
+            self.pattern_ascii = Some(bytes);
+        }
+    }
+
+    /// Checks whether `text` can possibly match the prepared pattern, i.e.
+    /// whether the pattern is a subsequence of the prepared text.
+    ///
+    /// This is much cheaper than indexing the text, and is done before any
+    /// other work for a candidate.
+    fn prefilter(&self, text: &str) -> bool {
+        match &self.pattern_ascii {
+            Some(pattern) if text.is_ascii() => {
+                is_subsequence_ascii(pattern, text.as_bytes(), self.case_sensitive)
+            }
+            _ => is_subsequence(
+                &self.pattern,
+                text,
+                self.case_sensitive,
+                self.config.normalization,
+            ),
+        }
+    }
+
+    /// Computes the score of matching the prepared pattern against `text`.
+    pub(crate) fn score_text(&mut self, text: &str) -> Option<i32> {
+        if self.pattern.is_empty() {
+            return Some(0);
+        }
+        if !self.prefilter(text) {
+            return None;
+        }
+        self.index_text(text);
+        self.fuzzy_score()
+    }
+
+    /// Matches the prepared pattern against `text`.
+    pub(crate) fn match_text(&mut self, text: &str, track_positions: bool) -> Option<Match> {
+        if self.pattern.is_empty() {
+            return Some(Match {
+                start: 0,
+                end: 0,
+                score: 0,
+                positions: vec![],
+                unit: self.config.unit(),
+            });
+        }
+
+        if !self.prefilter(text) {
+            return None;
+        }
+
+        // Phase 1 & 2: Indexing and bonus calculation
+        self.index_text(text);
+        self.fuzzy(track_positions)
+    }
+
+    /// Prepares `text` for matching against the current pattern.
+    fn index_text(&mut self, text: &str) {
+        self.text.index(text, self.case_sensitive, &self.config);
+    }
+
+    /// Fuzzy matches the prepared pattern against the indexed text, using the
+    /// configured algorithm.
+    fn fuzzy(&mut self, track_positions: bool) -> Option<Match> {
+        if self.use_greedy() {
+            return self.fuzzy_greedy(track_positions);
+        }
+        if !track_positions {
+            return self
+                .fuzzy_optimal_score()
+                .map(|score| self.to_match(score, vec![]));
+        }
+        self.fuzzy_optimal()
+    }
+
+    /// Computes the score of fuzzy matching the prepared pattern against the
+    /// indexed text, using the configured algorithm.
+    fn fuzzy_score(&mut self) -> Option<i32> {
+        if self.use_greedy() {
+            return self.fuzzy_greedy(false).map(|m| m.score);
+        }
+        self.fuzzy_optimal_score()
+    }
+
+    /// Determines whether the greedy algorithm should be used for the
+    /// prepared pattern and the indexed text.
+    fn use_greedy(&self) -> bool {
+        match self.config.algorithm {
+            Algorithm::Optimal => false,
+            Algorithm::Greedy => true,
+            Algorithm::Auto { max_cells } => self.pattern.len() * self.text.len() > max_cells,
+        }
+    }
+
+    /// Fuzzy matches the prepared pattern against the indexed text.
+    ///
+    /// Every character of the pattern must be matched, in order. Among all
+    /// such alignments, the one with the highest score is chosen.
+    fn fuzzy_optimal(&mut self) -> Option<Match> {
+        // Phase 3: Find where each pattern character can first be matched
+        if !self.find_first() {
+            return None;
+        }
+
+        let scoring = self.config.scoring;
+        let (pattern, text, bonus) = (&self.pattern, &self.text.chars, &self.text.bonus);
+        let (m, n) = (pattern.len(), self.text.len());
+        let first = &self.first;
+
+        // Phase 4: Score matrix calculation
+        //
+        // `h[i][j]` is the best score of matching `pattern[..=i]` within
+        // `text[..=j]`, and `c[i][j]` the length of the run of consecutive
+        // matches ending at `text[j]` if that score was reached by matching
+        // `pattern[i]` there, or 0 otherwise.
+        let (h, c) = (&mut self.h, &mut self.c);
+        h.resize(m * n, 0);
+        c.resize(m * n, 0);
+
+        // The last pattern character is matched at `first[m - 1]` at the
+        // latest, so the backtrace starts inside the computed region even if
+        // every cell of the last row scores 0.
+        let (mut max_score, mut max_j) = (0, first[m - 1]);
+
+        for i in 0..m {
+            let row = i * n;
+            for j in first[i]..n {
+                let left = (j > first[i]).then(|| (h[row + j - 1], c[row + j - 1]));
+                let diag = if i > 0 {
+                    (h[row - n + j - 1], c[row - n + j - 1])
+                } else {
+                    (0, 0)
+                };
+                let (score, consecutive) = score_cell(
+                    &scoring,
+                    bonus,
+                    i == 0,
+                    pattern[i] == text[j],
+                    j,
+                    left,
+                    diag,
+                );
+                h[row + j] = score;
+                c[row + j] = consecutive;
+
+                if i == m - 1 && score > max_score {
+                    max_score = score;
+                    max_j = j;
+                }
+            }
+        }
+
+        // Phase 5: Backtracing
+        //
+        // `pattern[i]` is matched at `first[i]` at the latest, so the
+        // backtrace never leaves the computed region of a row.
+        let mut positions = Vec::with_capacity(m);
+        let (mut i, mut j) = (m - 1, max_j);
+        loop {
+            if c[i * n + j] > 0 || j == first[i] {
+                positions.push(j);
+                if i == 0 {
+                    break;
+                }
+                i -= 1;
+            }
+            j -= 1;
+        }
+        positions.reverse();
+
+        Some(self.to_match(max_score, positions))
+    }
+
+    /// Computes the score of fuzzy matching the prepared pattern against the
+    /// indexed text, without positions.
+    ///
+    /// This computes the same cells as [`Matcher::fuzzy_optimal`], but only keeps the
+    /// previous and the current row of the score matrix, so it uses O(n)
+    /// memory and skips backtracing.
+    fn fuzzy_optimal_score(&mut self) -> Option<i32> {
+        if !self.find_first() {
+            return None;
+        }
+
+        let scoring = self.config.scoring;
+        let (pattern, text, bonus) = (&self.pattern, &self.text.chars, &self.text.bonus);
+        let (m, n) = (pattern.len(), self.text.len());
+        let first = &self.first;
+
+        // The two rows are stored back to back in `h` and `c`.
+        let (h, c) = (&mut self.h, &mut self.c);
+        h.resize(2 * n, 0);
+        c.resize(2 * n, 0);
+        let (mut h_prev, mut h_curr) = h.split_at_mut(n);
+        let (mut c_prev, mut c_curr) = c.split_at_mut(n);
+
+        let mut max_score = 0;
+
+        for i in 0..m {
+            for j in first[i]..n {
+                let left = (j > first[i]).then(|| (h_curr[j - 1], c_curr[j - 1]));
+                let diag = if i > 0 {
+                    (h_prev[j - 1], c_prev[j - 1])
+                } else {
+                    (0, 0)
+                };
+                let (score, consecutive) = score_cell(
+                    &scoring,
+                    bonus,
+                    i == 0,
+                    pattern[i] == text[j],
+                    j,
+                    left,
+                    diag,
+                );
+                h_curr[j] = score;
+                c_curr[j] = consecutive;
+
+                if i == m - 1 && score > max_score {
+                    max_score = score;
+                }
+            }
+            std::mem::swap(&mut h_prev, &mut h_curr);
+            std::mem::swap(&mut c_prev, &mut c_curr);
+        }
+
+        Some(max_score)
+    }
+
+    /// Fuzzy matches the prepared pattern against the indexed text with a
+    /// greedy scan, in O(n) time.
+    ///
+    /// A forward scan finds the first occurrence of the pattern as a
+    /// subsequence, and a backward scan from its end shrinks it to the
+    /// shortest window ending there. Only that window is scored, so the
+    /// result may score lower than the optimal alignment.
+    fn fuzzy_greedy(&self, track_positions: bool) -> Option<Match> {
+        let (pattern, text) = (&self.pattern, &self.text.chars);
+        let m = pattern.len();
+
+        // Forward scan
+        let mut i = 0;
+        let mut end = None;
+        for (j, &tc) in text.iter().enumerate() {
+            if tc == pattern[i] {
+                i += 1;
+                if i == m {
+                    end = Some(j + 1);
+                    break;
+                }
+            }
+        }
+        let end = end?;
+
+        // Backward scan
+        let mut i = m;
+        let mut start = end;
+        while i > 0 {
+            start -= 1;
+            if text[start] == pattern[i - 1] {
+                i -= 1;
+            }
+        }
+
+        let mut i = 0;
+        let positions = (start..end).filter(|&j| {
+            let matched = i < m && text[j] == pattern[i];
+            if matched {
+                i += 1;
+            }
+            matched
+        });
+
+        if !track_positions {
+            return Some(self.to_match(self.score_positions(positions), vec![]));
+        }
+        let positions: Vec<usize> = positions.collect();
+        Some(self.to_match(self.score_positions(positions.iter().copied()), positions))
+    }
+
+    /// Matches `pattern` against `text` with the local alignment used before
+    /// every pattern character had to be matched.
+    ///
+    /// Pattern characters may be skipped, so e.g. "abc" matches "axx", and
+    /// runs of consecutive matches earn no extra bonus. This only backs the
+    /// deprecated [`crate::fuzzy_match`], which keeps its results while
+    /// callers migrate to [`crate::fuzzy_find`].
+    pub(crate) fn match_partial(&mut self, text: &str, pattern: &str) -> Option<Match> {
+        self.set_pattern(pattern);
+        if self.pattern.is_empty() {
+            return Some(self.to_match(0, vec![]));
+        }
+        self.index_text(text);
+
+        let scoring = self.config.scoring;
+        let (pattern, text, bonus) = (&self.pattern, &self.text.chars, &self.text.bonus);
+        let (m, n) = (pattern.len(), self.text.len());
+        if m > n {
+            return None;
+        }
+
+        // `h[i][j]` is the best score of matching a part of `pattern[..i]`
+        // ending within `text[..j]`, with an extra row and column for the
+        // empty prefixes.
+        let width = n + 1;
+        let h = &mut self.h;
+        h.clear();
+        h.resize((m + 1) * width, 0);
+        for i in 1..=m {
+            h[i * width] = scoring.score_gap_start + (i as i32 - 1) * scoring.score_gap_extension;
+        }
+
+        let (mut max_score, mut max_i, mut max_j) = (0, 0, 0);
+        for i in 1..=m {
+            for j in 1..=n {
+                let score = if pattern[i - 1] == text[j - 1] {
+                    let b = if i == 1 {
+                        bonus[j - 1] * scoring.bonus_first_char_multiplier
+                    } else {
+                        bonus[j - 1]
+                    };
+                    h[(i - 1) * width + j - 1] + scoring.score_match + b
+                } else {
+                    std::cmp::max(
+                        h[i * width + j - 1] + scoring.score_gap_extension,
+                        h[(i - 1) * width + j] + scoring.score_gap_start,
+                    )
+                };
+                h[i * width + j] = std::cmp::max(0, score);
+
+                if h[i * width + j] > max_score {
+                    (max_score, max_i, max_j) = (h[i * width + j], i, j);
+                }
+            }
+        }
+
+        if max_score == 0 {
+            return None;
+        }
+
+        let mut positions = Vec::new();
+        let (mut i, mut j) = (max_i, max_j);
+        while i > 0 && j > 0 {
+            if pattern[i - 1] == text[j - 1] {
+                positions.push(j - 1);
+                i -= 1;
+                j -= 1;
+            } else if h[i * width + j - 1] + scoring.score_gap_extension == h[i * width + j] {
+                j -= 1;
+            } else {
+                i -= 1;
+            }
+        }
+        positions.reverse();
+
+        Some(self.to_match(max_score, positions))
+    }
+
+    /// Finds the first position at which each pattern character can be
+    /// matched, in order.
+    ///
+    /// Cells of the score matrix to the left of these positions can never be
+    /// part of an alignment, so they are not computed.
+    ///
+    /// # Returns
+    ///
+    /// `false` if the pattern is not a subsequence of the indexed text.
+    fn find_first(&mut self) -> bool {
+        let (pattern, text) = (&self.pattern, &self.text.chars);
+        if pattern.len() > text.len() {
+            return false;
+        }
+
+        self.first.clear();
+        let mut j = 0;
+        for &pc in pattern {
+            while j < text.len() && text[j] != pc {
+                j += 1;
+            }
+            if j == text.len() {
+                return false;
+            }
+            self.first.push(j);
+            j += 1;
+        }
+        true
+    }
+
+    /// Matches the prepared pattern as a contiguous substring of the indexed
+    /// text, anchored according to `anchor`.
+    ///
+    /// If the pattern occurs several times, the occurrence with the highest
+    /// score is returned.
+    fn exact(&self, anchor: Anchor, track_positions: bool) -> Option<Match> {
+        let (pattern, text) = (&self.pattern, &self.text.chars);
+        let (m, n) = (pattern.len(), self.text.len());
+
+        if m > n {
+            return None;
+        }
+
+        let candidates = match anchor {
+            Anchor::None => 0..n - m + 1,
+            Anchor::Start => 0..1,
+            Anchor::End => n - m..n - m + 1,
+            Anchor::Both if m == n => 0..1,
+            Anchor::Both => return None,
+        };
+
+        let (start, score) = candidates
+            .filter(|&start| text[start..start + m] == pattern[..])
+            .map(|start| (start, self.score_positions(start..start + m)))
+            .fold(
+                None,
+                |best: Option<(usize, i32)>, (start, score)| match best {
+                    Some((_, best_score)) if best_score >= score => best,
+                    _ => Some((start, score)),
+                },
+            )?;
+
+        if !track_positions {
+            return Some(self.to_match(score, vec![]));
+        }
+
+        Some(self.to_match(score, (start..start + m).collect()))
+    }
+
+    /// Calculates the score of matching the pattern at `positions` in the
+    /// indexed text, using the same rules as the score matrix. Like the cells
+    /// of the matrix, the running score never drops below 0.
+    fn score_positions(&self, positions: impl IntoIterator<Item = usize>) -> i32 {
+        let scoring = &self.config.scoring;
+        let bonus = &self.text.bonus;
+
+        let mut score = 0;
+        let mut prev: Option<usize> = None;
+        let (mut consecutive, mut first_bonus) = (0, 0);
+
+        for (i, j) in positions.into_iter().enumerate() {
+            if let Some(prev) = prev {
+                if j > prev + 1 {
+                    let gap = (j - prev - 1) as i32;
+                    let penalty = scoring.score_gap_start + (gap - 1) * scoring.score_gap_extension;
+                    score = std::cmp::max(0, score + penalty);
+                    consecutive = 0;
+                }
+            }
+
+            let mut b = bonus[j];
+            if consecutive == 0 {
+                first_bonus = b;
+            } else {
+                if b >= scoring.bonus_boundary && b > first_bonus {
+                    first_bonus = b;
+                }
+                b = b.max(first_bonus).max(scoring.bonus_consecutive);
+            }
+
+            score += scoring.score_match;
+            if i == 0 {
+                score += b * scoring.bonus_first_char_multiplier;
+            } else {
+                score += b;
+            }
+
+            score = std::cmp::max(0, score);
+            consecutive += 1;
+            prev = Some(j);
+        }
+
+        score
+    }
+
+    /// Builds a `Match` from positions in the indexed text.
+    fn to_match(&self, score: i32, positions: Vec<usize>) -> Match {
+        if positions.is_empty() {
+            return Match {
+                start: 0,
+                end: 0,
+                score,
+                positions,
+                unit: self.config.unit(),
+            };
+        }
+
+        // Map positions back to the characters of the original text. Several
+        // matched characters may derive from the same original character.
+        let sources = &self.text.sources;
+        let mut positions: Vec<usize> = positions.into_iter().map(|k| sources[k]).collect();
+        positions.dedup();
+
+        Match {
+            start: positions[0],
+            end: positions[positions.len() - 1] + 1,
+            score,
+            positions,
+            unit: self.config.unit(),
+        }
+    }
+
+    /// Matches an extended search `query` against `text`.
+    ///
+    /// Every term of the query must match for the text to match. The score is
+    /// the sum of the scores of the matching terms, and the positions are the
+    /// union of their positions. Inverse terms contribute neither.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The text to search in.
+    /// * `query` - The parsed query to search for.
+    ///
+    /// # Returns
+    ///
+    /// `Some(Match)` if `query` matches `text`, `None` otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rizzer::{Matcher, MatcherConfig, Query};
+    ///
+    /// let mut matcher = Matcher::new(MatcherConfig::default());
+    /// let query = Query::parse("^src .rs$ !test");
+    /// assert!(matcher.match_query("src/matcher.rs", &query).is_some());
+    /// assert!(matcher.match_query("src/test.rs", &query).is_none());
+    /// ```
+    pub fn match_query(&mut self, text: &str, query: &Query) -> Option<Match> {
+        let track_positions = self.config.track_positions;
+        let mut score = 0;
+        let mut positions = Vec::new();
+
+        for group in &query.groups {
+            let mut best: Option<Match> = None;
+            for term in group {
+                if let Some(m) = self.match_term(text, term, track_positions) {
+                    if best.as_ref().is_none_or(|b| m.score > b.score) {
+                        best = Some(m);
+                    }
+                }
+            }
+            let best = best?;
+            score += best.score;
+            positions.extend(best.positions);
+        }
+
+        positions.sort_unstable();
+        positions.dedup();
+
+        Some(match (positions.first(), positions.last()) {
+            (Some(&start), Some(&end)) => Match {
+                start,
+                end: end + 1,
+                score,
+                positions,
+                unit: self.config.unit(),
+            },
+            _ => Match {
+                start: 0,
+                end: 0,
+                score,
+                positions,
+                unit: self.config.unit(),
+            },
+        })
+    }
+
+    /// Matches a single query term against `text`.
+    ///
+    /// Inverse terms yield an empty `Match` with a score of 0 when the
+    /// underlying term does not match. A term that folds to an empty pattern,
+    /// such as a lone combining mark, is ignored like an empty token and
+    /// yields an empty `Match` as well.
+    fn match_term(&mut self, text: &str, term: &Term, track_positions: bool) -> Option<Match> {
+        self.set_pattern(&term.text);
+        if self.pattern.is_empty() {
+            return Some(Match {
+                start: 0,
+                end: 0,
+                score: 0,
+                positions: vec![],
+                unit: self.config.unit(),
+            });
+        }
+
+        let m = if !self.prefilter(text) {
+            None
+        } else {
+            self.index_text(text);
+            self.match_kind(term, track_positions && !term.inverse)
+        };
+
+        match (m, term.inverse) {
+            (Some(m), false) => Some(m),
+            (None, true) => Some(Match {
+                start: 0,
+                end: 0,
+                score: 0,
+                positions: vec![],
+                unit: self.config.unit(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Matches the prepared pattern against the indexed text according to the
+    /// kind of `term`.
+    fn match_kind(&mut self, term: &Term, track_positions: bool) -> Option<Match> {
+        match term.kind {
+            TermKind::Fuzzy => self.fuzzy(track_positions),
+            TermKind::Exact => self.exact(Anchor::None, track_positions),
+            TermKind::Prefix => self.exact(Anchor::Start, track_positions),
+            TermKind::Suffix => self.exact(Anchor::End, track_positions),
+            TermKind::Equal => self.exact(Anchor::Both, track_positions),
+        }
+    }
+}
+
+/// Computes one cell of the score matrix.
+///
+/// # Arguments
+///
+/// * `scoring` - The scores and bonuses.
+/// * `bonus` - The bonus of every character of the text.
+/// * `first_row` - Whether the cell belongs to the first pattern character.
+/// * `is_match` - Whether the pattern and text characters of the cell are equal.
+/// * `j` - The index of the text character of the cell.
+/// * `left` - The score and run length of the cell to the left, if it was
+///   computed. Without it, a matching character is always matched.
+/// * `diag` - The score and run length of the cell diagonally up and to the left.
+///
+/// # Returns
+///
+/// The score of the cell and the length of the run of consecutive matches
+/// ending in it, which is 0 if the pattern character is not matched here.
+#[inline]
+fn score_cell(
+    scoring: &Scoring,
+    bonus: &[i32],
+    first_row: bool,
+    is_match: bool,
+    j: usize,
+    left: Option<(i32, u32)>,
+    diag: (i32, u32),
+) -> (i32, u32) {
+    // Without a cell to the left, the pattern character cannot have been
+    // matched earlier, so it must be matched here.
+    let gap = left.map(|(left_h, left_c)| {
+        if left_c > 0 {
+            left_h + scoring.score_gap_start
+        } else {
+            left_h + scoring.score_gap_extension
+        }
+    });
+
+    let (mut score, mut consecutive) = (gap.unwrap_or(0), 0);
+    if is_match {
+        let (diag_h, mut run) = if first_row {
+            (0, 1)
+        } else {
+            (diag.0, diag.1 + 1)
+        };
+
+        let mut b = bonus[j];
+        if first_row {
+            b *= scoring.bonus_first_char_multiplier;
+        } else if run > 1 {
+            // Carry the bonus of the first character of the run along,
+            // unless this character starts a new word.
+            let first_bonus = bonus[j + 1 - run as usize];
+            if b >= scoring.bonus_boundary && b > first_bonus {
+                run = 1;
+            } else {
+                b = b.max(first_bonus).max(scoring.bonus_consecutive);
+            }
+        }
+
+        let matched = diag_h + scoring.score_match + b;
+        if gap.is_none_or(|gap| matched >= gap) {
+            score = matched;
+            consecutive = run;
+        }
+    }
+
+    (std::cmp::max(0, score), consecutive)
+}
+
+/// Where an exact match must occur in the text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Anchor {
+    None,
+    Start,
+    End,
+    Both,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_many() {
+        let mut matcher = Matcher::new(MatcherConfig::default());
+        let candidates = ["algorithm", "xyz", "alarm"];
+        let matches = matcher.match_many(&candidates, "alm");
+        let indices: Vec<usize> = matches.iter().map(|(i, _)| *i).collect();
+        assert_eq!(indices, vec![0, 2]);
+    }
+
+    #[test]
+    fn test_score_one_matches_match_one() {
+        let mut matcher = Matcher::new(MatcherConfig::default());
+        let m = matcher.match_one("algorithm", "alm").unwrap();
+        assert_eq!(matcher.score_one("algorithm", "alm"), Some(m.score));
+    }
+
+    #[test]
+    fn test_track_positions_disabled() {
+        let mut matcher = Matcher::new(MatcherConfig {
+            track_positions: false,
+            ..Default::default()
+        });
+        let m = matcher
+            .match_one("abcdefghijklmnopqrstuvwxyz", "ace")
+            .unwrap();
+        assert!(m.score > 0);
+        assert!(m.positions.is_empty());
+    }
+
+    #[test]
+    fn test_match_accented() {
+        let mut matcher = Matcher::new(MatcherConfig::default());
+        let m = matcher.match_one("Crème brûlée", "creme brulee").unwrap();
+        assert_eq!(m.positions, (0..12).collect::<Vec<_>>());
+        assert_eq!((m.start, m.end), (0, 12));
+
+        let m = matcher.match_one("déjà vu", "vu").unwrap();
+        assert_eq!(m.positions, vec![5, 6]);
+    }
+
+    #[test]
+    fn test_match_normalization_levels() {
+        let config = |normalization| MatcherConfig {
+            normalization,
+            ..Default::default()
+        };
+
+        let mut matcher = Matcher::new(config(Normalization::None));
+        assert!(matcher.match_one("Straße", "strasse").is_none());
+
+        let mut matcher = Matcher::new(config(Normalization::CaseFold));
+        let m = matcher.match_one("Straße", "strasse").unwrap();
+        assert_eq!(m.positions, vec![0, 1, 2, 3, 4, 5]);
+        assert!(matcher.match_one("Crème", "creme").is_none());
+
+        let mut matcher = Matcher::new(config(Normalization::StripDiacritics));
+        assert!(matcher.match_one("Crème", "creme").is_some());
+        assert!(matcher.match_one("ＦＵＬＬ", "full").is_none());
+
+        let mut matcher = Matcher::new(config(Normalization::Compatibility));
+        let m = matcher.match_one("ＦＵＬＬ ﬁle", "full file").unwrap();
+        assert_eq!(m.positions, vec![0, 1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    #[cfg(feature = "transliterate")]
+    #[test]
+    fn test_match_transliterated() {
+        let mut matcher = Matcher::new(MatcherConfig {
+            normalization: Normalization::Transliterate,
+            ..Default::default()
+        });
+        let m = matcher.match_one("Город Москва", "moskva").unwrap();
+        assert_eq!(m.positions, vec![6, 7, 8, 9, 10, 11]);
+
+        // Several ASCII characters derived from one character map back to it.
+        let m = matcher.match_one("Щука", "shchu").unwrap();
+        assert_eq!(m.positions, vec![0, 1]);
+        assert!(matcher.match_one("Москва", "москва").is_some());
+    }
+
+    #[cfg(feature = "grapheme")]
+    #[test]
+    fn test_match_graphemes() {
+        let mut matcher = Matcher::new(MatcherConfig {
+            graphemes: true,
+            ..Default::default()
+        });
+
+        // A decomposed 'é' and a flag are single units.
+        let text = "Cafe\u{301} 🇩🇪 de";
+        let m = matcher.match_one(text, "café de").unwrap();
+        assert_eq!(m.positions, vec![0, 1, 2, 3, 4, 7, 8]);
+        assert_eq!(&text[m.byte_ranges(text)[3].clone()], "e\u{301}");
+
+        let m = matcher.match_one(text, "🇩🇪").unwrap();
+        assert_eq!(m.positions, vec![5]);
+        assert_eq!((m.start, m.end), (5, 6));
+        assert_eq!(&text[m.byte_ranges(text)[0].clone()], "🇩🇪");
+
+        let mut chars = Matcher::new(MatcherConfig::default());
+        assert_eq!(chars.match_one(text, "🇩🇪").unwrap().positions, vec![6, 7]);
+
+        // Characters are still compared one by one.
+        let mut matcher = Matcher::new(MatcherConfig {
+            graphemes: true,
+            normalization: Normalization::None,
+            ..Default::default()
+        });
+        assert_eq!(
+            matcher.match_one("e\u{301}", "e").unwrap().positions,
+            vec![0]
+        );
+        assert_eq!(matcher.match_one("x🇩🇪", "🇩").unwrap().positions, vec![1]);
+    }
+
+    #[cfg(not(feature = "grapheme"))]
+    #[test]
+    fn test_graphemes_ignored_without_feature() {
+        let mut matcher = Matcher::new(MatcherConfig {
+            graphemes: true,
+            ..Default::default()
+        });
+        let m = matcher.match_one("e\u{301}abc", "abc").unwrap();
+        assert_eq!((m.unit, m.positions), (Unit::Char, vec![2, 3, 4]));
+    }
+
+    #[test]
+    fn test_match_cjk() {
+        let mut matcher = Matcher::new(MatcherConfig::default());
+        let m = matcher.match_one("東京都渋谷区", "渋谷").unwrap();
+        assert_eq!(m.positions, vec![3, 4]);
+        assert_eq!((m.start, m.end), (3, 5));
+
+        // The pattern is longer in characters than the text, but not in bytes.
+        assert_eq!(matcher.match_one("東京", "abcdefg"), None);
+    }
+
+    #[test]
+    fn test_match_emoji() {
+        let mut matcher = Matcher::new(MatcherConfig::default());
+        let m = matcher.match_one("🦀 rust 🦀", "rust").unwrap();
+        assert_eq!(m.positions, vec![2, 3, 4, 5]);
+
+        let m = matcher.match_one("🦀🐍🦀", "🐍").unwrap();
+        assert_eq!(m.positions, vec![1]);
+    }
+
+    #[test]
+    fn test_match_expanding_lowercase() {
+        let mut matcher = Matcher::new(MatcherConfig::default());
+        let m = matcher.match_one("İstanbul", "ist").unwrap();
+        assert_eq!(m.positions, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_case_matching() {
+        let config = |case_matching| MatcherConfig {
+            case_matching,
+            ..Default::default()
+        };
+
+        let mut matcher = Matcher::new(config(CaseMatching::Respect));
+        assert!(matcher.match_one("FOOBAR", "foo").is_none());
+        assert!(matcher.match_one("FooBar", "FB").is_some());
+
+        let mut matcher = Matcher::new(config(CaseMatching::Ignore));
+        assert!(matcher.match_one("FooBar", "foo").is_some());
+        assert!(matcher.match_one("foobar", "FB").is_some());
+
+        let mut matcher = Matcher::new(config(CaseMatching::Smart));
+        assert!(matcher.match_one("FOOBAR", "foo").is_some());
+        assert!(matcher.match_one("FooBar", "FB").is_some());
+        assert!(matcher.match_one("foobar", "FB").is_none());
+    }
+
+    #[test]
+    fn test_match_query() {
+        let mut matcher = Matcher::new(MatcherConfig::default());
+        let query = Query::parse("^src .rs$ !test");
+        let m = matcher.match_query("src/matcher.rs", &query).unwrap();
+        assert_eq!(m.positions, vec![0, 1, 2, 11, 12, 13]);
+        assert_eq!((m.start, m.end), (0, 14));
+        assert!(matcher.match_query("src/test.rs", &query).is_none());
+        assert!(matcher.match_query("lib/matcher.rs", &query).is_none());
+        assert!(matcher.match_query("src/matcher.go", &query).is_none());
+
+        let query = Query::parse("'cher ^lib$");
+        assert!(matcher.match_query("src/matcher.rs", &query).is_none());
+        assert!(matcher.match_query("lib", &Query::parse("^lib$")).is_some());
+    }
+
+    #[test]
+    fn test_match_query_or_groups() {
+        let mut matcher = Matcher::new(MatcherConfig::default());
+        let query = Query::parse("^core go$ | rb$ | py$");
+        assert!(matcher.match_query("core/main.go", &query).is_some());
+        assert!(matcher.match_query("core/main.py", &query).is_some());
+        assert!(matcher.match_query("core/main.rs", &query).is_none());
+        assert!(matcher.match_query("lib/main.go", &query).is_none());
+    }
+
+    #[test]
+    fn test_match_query_scores() {
+        let mut matcher = Matcher::new(MatcherConfig::default());
+        let text = "src/matcher.rs";
+        let fuzzy = matcher.match_one(text, "mat").unwrap().score;
+        let exact = matcher.match_one(text, "rs").unwrap().score;
+        let m = matcher
+            .match_query(text, &Query::parse("mat 'rs !xyz"))
+            .unwrap();
+        assert_eq!(m.score, fuzzy + exact);
+
+        // Only inverse terms: matches with no positions.
+        let m = matcher.match_query(text, &Query::parse("!xyz")).unwrap();
+        assert_eq!(m.score, 0);
+        assert!(m.positions.is_empty());
+    }
+
+    #[test]
+    fn test_match_query_empty_folded_term() {
+        // A lone combining mark folds to nothing and is ignored.
+        for algorithm in [Algorithm::Optimal, Algorithm::Greedy] {
+            let mut matcher = Matcher::new(MatcherConfig {
+                algorithm,
+                ..Default::default()
+            });
+            for query in ["\u{301}", "!\u{301}", "'\u{301}"] {
+                let m = matcher.match_query("abc", &Query::parse(query)).unwrap();
+                assert_eq!((m.score, m.positions), (0, vec![]));
+            }
+            let m = matcher
+                .match_query("abc", &Query::parse("ab \u{301}"))
+                .unwrap();
+            assert_eq!(m.positions, vec![0, 1]);
+        }
+    }
+
+    #[test]
+    fn test_consecutive_bonus() {
+        let mut matcher = Matcher::new(MatcherConfig::default());
+        let consecutive = matcher.score_one("xabcx", "abc").unwrap();
+        let scattered = matcher.score_one("xaxbxcx", "abc").unwrap();
+        assert!(consecutive > scattered + 8);
+
+        let m = matcher.match_one("xabxabc", "abc").unwrap();
+        assert_eq!(m.positions, vec![4, 5, 6]);
+    }
+
+    #[test]
+    fn test_whole_pattern_must_match() {
+        let mut matcher = Matcher::new(MatcherConfig::default());
+        assert!(matcher.match_one("axx", "abc").is_none());
+        assert!(matcher.match_one("cba", "abc").is_none());
+        assert!(matcher.match_one("FooBar", "fox").is_none());
+    }
+
+    #[test]
+    fn test_exact_score_matches_fuzzy() {
+        let mut matcher = Matcher::new(MatcherConfig::default());
+        for (text, pattern) in [
+            ("src/matcher.rs", "match"),
+            ("foo_bar", "o_b"),
+            ("abc", "abc"),
+        ] {
+            let fuzzy = matcher.match_one(text, pattern).unwrap();
+            let exact = matcher
+                .match_query(text, &Query::parse(&format!("'{pattern}")))
+                .unwrap();
+            assert_eq!(exact, fuzzy);
+        }
+    }
+
+    #[test]
+    fn test_camel_case_boundaries() {
+        let mut matcher = Matcher::new(MatcherConfig::default());
+        let camel = matcher.score_one("fooBar", "b").unwrap();
+        let flat = matcher.score_one("foobar", "b").unwrap();
+        assert!(camel > flat);
+
+        let m = matcher.match_one("getBuffer", "gb").unwrap();
+        assert_eq!(m.positions, vec![0, 3]);
+        let m = matcher.match_one("fooBarBaz", "baz").unwrap();
+        assert_eq!(m.positions, vec![6, 7, 8]);
+    }
+
+    #[test]
+    fn test_path_mode() {
+        let mut matcher = Matcher::new(MatcherConfig {
+            scoring: Scoring::path(),
+            path_mode: PathMode::Unix,
+            ..Default::default()
+        });
+
+        // Matches in the basename are preferred.
+        let m = matcher.match_one("src/matcher/mod.rs", "m").unwrap();
+        assert_eq!(m.positions, vec![12]);
+
+        // Separators are stronger boundaries than whitespace.
+        let m = matcher.match_one("my notes/notes.txt", "n").unwrap();
+        assert_eq!(m.positions, vec![9]);
+
+        // Ties favor shorter candidates.
+        let candidates = ["lib/foo/bar.rs", "foo/bar.rs", "x/foo/bar.rs"];
+        let mut matches = matcher.match_many(&candidates, "bar");
+        matcher.sort_matches(&candidates, &mut matches);
+        let order: Vec<usize> = matches.iter().map(|(i, _)| *i).collect();
+        assert_eq!(order, vec![1, 2, 0]);
+    }
+
+    #[test]
+    fn test_path_mode_windows() {
+        let mut matcher = Matcher::new(MatcherConfig {
+            scoring: Scoring::path(),
+            path_mode: PathMode::Windows,
+            ..Default::default()
+        });
+        let m = matcher.match_one("C:\\src\\main.rs", "m").unwrap();
+        assert_eq!(m.positions, vec![7]);
+    }
+
+    #[test]
+    fn test_custom_scoring() {
+        let text = "ab-b";
+        let mut matcher = Matcher::new(MatcherConfig::default());
+        assert_eq!(matcher.match_one(text, "ab").unwrap().positions, vec![0, 1]);
+
+        // A large enough boundary bonus outweighs the consecutive match.
+        let mut matcher = Matcher::new(MatcherConfig {
+            scoring: Scoring {
+                bonus_boundary: 40,
+                ..Scoring::default()
+            },
+            ..Default::default()
+        });
+        assert_eq!(matcher.match_one(text, "ab").unwrap().positions, vec![0, 3]);
+    }
+
+    #[test]
+    fn test_custom_scoring_stays_in_computed_cells() {
+        let cases = [
+            (
+                Scoring {
+                    score_match: -20,
+                    ..Scoring::default()
+                },
+                "a b c",
+                vec![0, 2, 4],
+            ),
+            (
+                Scoring {
+                    bonus_boundary_white: -50,
+                    ..Scoring::default()
+                },
+                "a b c",
+                vec![0, 2, 4],
+            ),
+            (
+                Scoring {
+                    score_gap_extension: 30,
+                    ..Scoring::default()
+                },
+                "xxabc",
+                vec![2, 3, 4],
+            ),
+        ];
+        for (scoring, text, positions) in cases {
+            let mut matcher = Matcher::new(MatcherConfig {
+                scoring,
+                algorithm: Algorithm::Optimal,
+                ..Default::default()
+            });
+            // Leave stale cells in the buffers.
+            matcher.match_one("abcabcabc xyz", "abc");
+            let m = matcher.match_one(text, "abc").unwrap();
+            assert_eq!(m.positions, positions, "{scoring:?}");
+        }
+    }
+
+    #[test]
+    fn test_zero_scores() {
+        let mut matcher = Matcher::new(MatcherConfig {
+            scoring: Scoring {
+                score_match: 0,
+                ..Scoring::default()
+            },
+            ..Default::default()
+        });
+        let m = matcher.match_one("xxa", "a").unwrap();
+        assert_eq!((m.score, m.positions), (0, vec![2]));
+    }
+
+    #[test]
+    fn test_score_only_path_matches_full_matrix() {
+        let texts = [
+            "src/matcher/mod.rs",
+            "fooBarBaz_qux-quux",
+            "aaaaabaaaabaaab",
+            "the quick brown fox jumps over the lazy dog",
+            "Crème brûlée / İstanbul / 東京都",
+            "abcabcabcabc",
+        ];
+        let patterns = [
+            "a", "ab", "aab", "mod", "fbq", "qx", "the dog", "ist", "東都", "cab", "zzz",
+        ];
+
+        let mut full = Matcher::new(MatcherConfig::default());
+        let mut linear = Matcher::new(MatcherConfig::default());
+        for text in texts {
+            for pattern in patterns {
+                let expected = full.match_one(text, pattern).map(|m| m.score);
+                assert_eq!(
+                    linear.score_one(text, pattern),
+                    expected,
+                    "{text:?} {pattern:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_greedy_algorithm() {
+        let mut matcher = Matcher::new(MatcherConfig {
+            algorithm: Algorithm::Greedy,
+            ..Default::default()
+        });
+
+        // The window is shrunk by the backward scan.
+        let m = matcher.match_one("a_b_xa_b_c", "abc").unwrap();
+        assert_eq!(m.positions, vec![5, 7, 9]);
+        assert_eq!(matcher.score_one("a_b_xa_b_c", "abc"), Some(m.score));
+        assert!(matcher.match_one("cba", "abc").is_none());
+
+        // The greedy window can miss a better alignment.
+        let mut optimal = Matcher::new(MatcherConfig {
+            algorithm: Algorithm::Optimal,
+            ..Default::default()
+        });
+        let text = "xaxbxc abc";
+        let greedy = matcher.match_one(text, "abc").unwrap();
+        let best = optimal.match_one(text, "abc").unwrap();
+        assert_eq!(greedy.positions, vec![1, 3, 5]);
+        assert_eq!(best.positions, vec![7, 8, 9]);
+        assert!(best.score > greedy.score);
+    }
+
+    #[test]
+    fn test_greedy_score_long_gap() {
+        let text = format!("a{}b", "x".repeat(200));
+        let greedy = Matcher::new(MatcherConfig {
+            algorithm: Algorithm::Greedy,
+            ..Default::default()
+        })
+        .score_one(&text, "ab");
+        let optimal = Matcher::new(MatcherConfig {
+            algorithm: Algorithm::Optimal,
+            ..Default::default()
+        })
+        .score_one(&text, "ab");
+        assert_eq!(greedy, optimal);
+        assert!(greedy.unwrap() > 0);
+    }
+
+    #[test]
+    fn test_auto_algorithm() {
+        let text = "xaxbxc abc";
+        let config = |max_cells| MatcherConfig {
+            algorithm: Algorithm::Auto { max_cells },
+            ..Default::default()
+        };
+
+        let m = Matcher::new(config(30)).match_one(text, "abc").unwrap();
+        assert_eq!(m.positions, vec![7, 8, 9]);
+        let m = Matcher::new(config(29)).match_one(text, "abc").unwrap();
+        assert_eq!(m.positions, vec![1, 3, 5]);
+    }
+
+    #[test]
+    fn test_prefilter() {
+        let mut matcher = Matcher::new(MatcherConfig::default());
+        matcher.set_pattern("fb");
+        assert!(matcher.pattern_ascii.is_some());
+        assert!(matcher.prefilter("FooBar"));
+        assert!(!matcher.prefilter("BarFoo"));
+        assert!(matcher.prefilter("Füße bar"));
+
+        matcher.set_pattern("東");
+        assert!(matcher.pattern_ascii.is_none());
+        assert!(matcher.prefilter("東京"));
+        assert!(!matcher.prefilter("京都"));
+    }
+}
@@ -0,0 +1,99 @@
+//! Cheap checks that reject texts which cannot match a pattern.
+//!
+//! In a typical picker the vast majority of candidates do not contain the
+//! characters of the pattern in order. These checks run before the text is
+//! indexed, so rejecting a candidate costs no allocation and no bonus or score
+//! matrix calculation.
+
+use memchr::{memchr, memchr2};
+
+use crate::text::{folded, Normalization};
+
+/// Checks whether the prepared `pattern` is a subsequence of `text` once
+/// `text` is folded the same way.
+///
+/// # Arguments
+///
+/// * `pattern` - The prepared pattern.
+/// * `text` - The original text.
+/// * `case_sensitive` - Whether the match should be case-sensitive.
+/// * `normalization` - How thoroughly characters are folded.
+pub(crate) fn is_subsequence(
+    pattern: &[char],
+    text: &str,
+    case_sensitive: bool,
+    normalization: Normalization,
+) -> bool {
+    let mut pattern = pattern.iter().peekable();
+    for c in text.chars() {
+        for c in folded(c, case_sensitive, normalization) {
+            match pattern.peek() {
+                Some(&&pc) if pc == c => {
+                    pattern.next();
+                }
+                Some(_) => {}
+                None => return true,
+            }
+        }
+    }
+    pattern.peek().is_none()
+}
+
+/// Checks whether the prepared ASCII `pattern` is a subsequence of the ASCII
+/// `text`, scanning for each pattern byte with `memchr`.
+///
+/// # Arguments
+///
+/// * `pattern` - The prepared pattern. When matching case-insensitively, it
+///   must be lowercase.
+/// * `text` - The original text.
+/// * `case_sensitive` - Whether the match should be case-sensitive.
+pub(crate) fn is_subsequence_ascii(pattern: &[u8], text: &[u8], case_sensitive: bool) -> bool {
+    let mut from = 0;
+    for &b in pattern {
+        let rest = &text[from..];
+        let found = if !case_sensitive && b.is_ascii_lowercase() {
+            memchr2(b, b.to_ascii_uppercase(), rest)
+        } else {
+            memchr(b, rest)
+        };
+        match found {
+            Some(i) => from += i + 1,
+            None => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_subsequence() {
+        let pattern: Vec<char> = "cafe".chars().collect();
+        let strip = Normalization::StripDiacritics;
+        assert!(is_subsequence(&pattern, "Le Café", false, strip));
+        assert!(!is_subsequence(
+            &pattern,
+            "Le Café",
+            false,
+            Normalization::None
+        ));
+        assert!(!is_subsequence(&pattern, "Le Café", true, strip));
+        assert!(!is_subsequence(&pattern, "efac", false, strip));
+
+        let pattern: Vec<char> = "i\u{307}s".chars().collect();
+        assert!(is_subsequence(&pattern, "İs", false, Normalization::None));
+    }
+
+    #[test]
+    fn test_is_subsequence_ascii() {
+        assert!(is_subsequence_ascii(b"fb", b"FooBar", false));
+        assert!(!is_subsequence_ascii(b"fb", b"FooBar", true));
+        assert!(is_subsequence_ascii(b"FB", b"FooBar", true));
+        assert!(is_subsequence_ascii(b"o_1", b"foo_bar1", false));
+        assert!(!is_subsequence_ascii(b"rf", b"foo_bar", false));
+        assert!(is_subsequence_ascii(b"", b"", false));
+    }
+}
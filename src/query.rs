@@ -0,0 +1,241 @@
+//! Parsing of fzf-style extended search queries.
+//!
+//! A query is split on spaces into terms that must all match. Each term can be
+//! modified with the following syntax:
+//!
+//! | Token     | Match type                 | Description                           |
+//! |-----------|----------------------------|---------------------------------------|
+//! | `sbtrkt`  | fuzzy-match                | Items that match `sbtrkt`             |
+//! | `'wild`   | exact-match                | Items that include `wild`             |
+//! | `^music`  | prefix-exact-match         | Items that start with `music`         |
+//! | `.mp3$`   | suffix-exact-match         | Items that end with `.mp3`            |
+//! | `^foo$`   | equal-match                | Items that are exactly `foo`          |
+//! | `!fire`   | inverse-exact-match        | Items that do not include `fire`      |
+//! | `!^music` | inverse-prefix-exact-match | Items that do not start with `music`  |
+//! | `!.mp3$`  | inverse-suffix-exact-match | Items that do not end with `.mp3`     |
+//! | `!'fire`  | inverse-fuzzy-match        | Items that do not match `fire`        |
+//!
+//! Terms separated by a lone `|` form an OR group, which matches if any of its
+//! terms match: `^core go$ | rb$ | py$`. A space can be included in a term by
+//! escaping it with a backslash. Tokens consisting only of modifiers, such as
+//! a lone `!` or `^`, are ignored.
+
+/// How a query term is matched against the text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TermKind {
+    /// The term is fuzzy matched.
+    Fuzzy,
+    /// The term must occur as a substring.
+    Exact,
+    /// The text must start with the term.
+    Prefix,
+    /// The text must end with the term.
+    Suffix,
+    /// The text must be equal to the term.
+    Equal,
+}
+
+/// A single term of a query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Term {
+    pub(crate) kind: TermKind,
+    /// Whether the term must *not* match.
+    pub(crate) inverse: bool,
+    pub(crate) text: String,
+}
+
+/// A parsed extended search query.
+///
+/// See the [module documentation](self) for the syntax.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Query {
+    /// Groups of terms that must all match. Each group matches if any of its
+    /// terms match.
+    pub(crate) groups: Vec<Vec<Term>>,
+}
+
+impl Query {
+    /// Parses an extended search query.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The query string.
+    ///
+    /// # Returns
+    ///
+    /// The parsed `Query`. Parsing never fails.
+    pub fn parse(query: &str) -> Query {
+        let mut groups: Vec<Vec<Term>> = Vec::new();
+        let mut join_next = false;
+
+        for token in split_tokens(query) {
+            if token == "|" {
+                join_next = !groups.is_empty();
+                continue;
+            }
+
+            let Some(term) = parse_term(&token) else {
+                continue;
+            };
+
+            match groups.last_mut() {
+                Some(group) if join_next => group.push(term),
+                _ => groups.push(vec![term]),
+            }
+            join_next = false;
+        }
+
+        Query { groups }
+    }
+
+    /// Returns `true` if the query has no terms, in which case it matches
+    /// every text.
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+}
+
+/// Splits a query on unescaped spaces, unescaping `\ ` into a literal space.
+fn split_tokens(query: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut chars = query.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&' ') => {
+                token.push(' ');
+                chars.next();
+            }
+            ' ' => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            _ => token.push(c),
+        }
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+
+    tokens
+}
+
+/// Parses a single token into a term.
+///
+/// # Returns
+///
+/// `None` if nothing remains of the token once its modifiers are removed, in
+/// which case the token is ignored.
+fn parse_term(token: &str) -> Option<Term> {
+    let mut text = token;
+    let mut kind = TermKind::Fuzzy;
+    let mut inverse = false;
+
+    if let Some(rest) = text.strip_prefix('!') {
+        inverse = true;
+        kind = TermKind::Exact;
+        text = rest;
+    }
+
+    let mut suffix = false;
+    if let Some(rest) = text.strip_suffix('$') {
+        suffix = true;
+        text = rest;
+    }
+
+    if let Some(rest) = text.strip_prefix('\'') {
+        // A quote makes a fuzzy term exact, and an inverse term fuzzy.
+        kind = if suffix {
+            TermKind::Suffix
+        } else if inverse {
+            TermKind::Fuzzy
+        } else {
+            TermKind::Exact
+        };
+        text = rest;
+    } else if let Some(rest) = text.strip_prefix('^') {
+        kind = if suffix {
+            TermKind::Equal
+        } else {
+            TermKind::Prefix
+        };
+        text = rest;
+    } else if suffix {
+        kind = TermKind::Suffix;
+    }
+
+    if text.is_empty() {
+        return None;
+    }
+
+    Some(Term {
+        kind,
+        inverse,
+        text: text.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn term(kind: TermKind, inverse: bool, text: &str) -> Term {
+        Term {
+            kind,
+            inverse,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_terms() {
+        let query = Query::parse("sbtrkt 'wild ^music .mp3$ ^foo$ !fire !^music !.mp3$ !'fire");
+        let terms: Vec<Term> = query.groups.into_iter().flatten().collect();
+        assert_eq!(
+            terms,
+            vec![
+                term(TermKind::Fuzzy, false, "sbtrkt"),
+                term(TermKind::Exact, false, "wild"),
+                term(TermKind::Prefix, false, "music"),
+                term(TermKind::Suffix, false, ".mp3"),
+                term(TermKind::Equal, false, "foo"),
+                term(TermKind::Exact, true, "fire"),
+                term(TermKind::Prefix, true, "music"),
+                term(TermKind::Suffix, true, ".mp3"),
+                term(TermKind::Fuzzy, true, "fire"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_or_groups() {
+        let query = Query::parse("^core go$ | rb$ | py$");
+        assert_eq!(
+            query.groups,
+            vec![
+                vec![term(TermKind::Prefix, false, "core")],
+                vec![
+                    term(TermKind::Suffix, false, "go"),
+                    term(TermKind::Suffix, false, "rb"),
+                    term(TermKind::Suffix, false, "py"),
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_edge_cases() {
+        assert!(Query::parse("").is_empty());
+        assert!(Query::parse("  !  ^ $ ").is_empty());
+        assert!(Query::parse("| |").is_empty());
+        assert_eq!(
+            Query::parse("foo\\ bar 'baz$").groups,
+            vec![
+                vec![term(TermKind::Fuzzy, false, "foo bar")],
+                vec![term(TermKind::Suffix, false, "baz")],
+            ]
+        );
+    }
+}
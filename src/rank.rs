@@ -0,0 +1,208 @@
+//! Deterministic ordering of matches.
+//!
+//! Matches are ranked by a chain of [`Tiebreak`] criteria, following fzf's
+//! `--tiebreak` option. Each criterion is only consulted when all previous ones
+//! are equal, and the index of the candidate always breaks the remaining ties,
+//! so rankings never depend on the sorting algorithm or on thread scheduling.
+
+use std::cmp::Ordering;
+
+use crate::text::units;
+use crate::{Match, Unit};
+
+/// A criterion used to rank matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Tiebreak {
+    /// Prefers a higher score.
+    Score,
+    /// Prefers a shorter candidate.
+    Length,
+    /// Prefers a match that begins earlier in the candidate.
+    Begin,
+    /// Prefers a match that ends closer to the end of the candidate.
+    End,
+    /// Prefers a candidate that comes earlier in the input.
+    Index,
+    /// Prefers a match in a shorter whitespace-delimited chunk of the
+    /// candidate.
+    Chunk,
+}
+
+impl Tiebreak {
+    /// The default chain: higher score first, then shorter candidates.
+    pub const DEFAULT: &'static [Tiebreak] = &[Tiebreak::Score, Tiebreak::Length];
+
+    /// Returns `true` if this criterion looks at where the match is in the
+    /// candidate, which requires the matched positions.
+    pub fn needs_positions(self) -> bool {
+        matches!(self, Tiebreak::Begin | Tiebreak::End | Tiebreak::Chunk)
+    }
+}
+
+/// The maximum number of criteria in a chain, excluding the final index.
+const MAX_CRITERIA: usize = 6;
+
+/// The rank of a match under a tiebreak chain. Smaller keys rank better.
+///
+/// # Example
+///
+/// ```
+/// use rizzer::{fuzzy_find, RankKey, Tiebreak};
+///
+/// let texts = ["abc def", "xabc"];
+/// let chain = [Tiebreak::Begin];
+/// let mut ranked: Vec<_> = texts
+///     .iter()
+///     .enumerate()
+///     .filter_map(|(i, text)| fuzzy_find(text, "abc", false, true).map(|m| (i, m)))
+///     .map(|(i, m)| (RankKey::new(&chain, texts[i], i, &m), i))
+///     .collect();
+/// ranked.sort();
+/// assert_eq!(ranked[0].1, 0);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RankKey([i64; MAX_CRITERIA + 1]);
+
+impl RankKey {
+    /// Computes the rank of a match.
+    ///
+    /// Only the first six criteria of `tiebreak` are used. `Begin`, `End` and
+    /// `Chunk` treat all matches without positions as equal. Lengths are
+    /// counted in the unit of `m`.
+    ///
+    /// # Arguments
+    ///
+    /// * `tiebreak` - The chain of criteria.
+    /// * `text` - The candidate that matched.
+    /// * `index` - The index of the candidate.
+    /// * `m` - The match.
+    pub fn new(tiebreak: &[Tiebreak], text: &str, index: usize, m: &Match) -> Self {
+        let located = (!m.positions.is_empty()).then_some(m);
+        let graphemes = m.unit == Unit::Grapheme;
+        Self::from_score(tiebreak, text, index, m.score, located, graphemes)
+    }
+
+    /// Computes the rank of a match of which only the score is known.
+    ///
+    /// Criteria that need positions are ignored unless `m` is given.
+    /// `graphemes` tells whether positions are indices of grapheme clusters.
+    pub(crate) fn from_score(
+        tiebreak: &[Tiebreak],
+        text: &str,
+        index: usize,
+        score: i32,
+        m: Option<&Match>,
+        graphemes: bool,
+    ) -> Self {
+        let mut key = [0; MAX_CRITERIA + 1];
+        let mut len = None;
+        let mut text_len = || *len.get_or_insert_with(|| units(text, graphemes).count());
+
+        for (value, criterion) in key.iter_mut().zip(tiebreak.iter().take(MAX_CRITERIA)) {
+            *value = match (criterion, m) {
+                (Tiebreak::Score, _) => -(score as i64),
+                (Tiebreak::Length, _) => text_len() as i64,
+                (Tiebreak::Index, _) => index as i64,
+                (Tiebreak::Begin, Some(m)) => m.start as i64,
+                (Tiebreak::End, Some(m)) => text_len().saturating_sub(m.end) as i64,
+                (Tiebreak::Chunk, Some(m)) => chunk_len(text, m, graphemes) as i64,
+                (_, None) => 0,
+            };
+        }
+        key[MAX_CRITERIA] = index as i64;
+
+        RankKey(key)
+    }
+}
+
+/// Returns the length of the whitespace-delimited chunk of `text` that
+/// contains the match.
+fn chunk_len(text: &str, m: &Match, graphemes: bool) -> usize {
+    let white: Vec<bool> = units(text, graphemes)
+        .map(|unit| unit.chars().all(char::is_whitespace))
+        .collect();
+    let (start, end) = (m.start.min(white.len()), m.end.min(white.len()));
+    let before = white[..start].iter().rev().take_while(|w| !**w).count();
+    let after = white[end..].iter().take_while(|w| !**w).count();
+    before + (end - start) + after
+}
+
+impl PartialOrd for Match {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Match {
+    /// Orders matches by quality, the better match being greater: a higher
+    /// score, then an earlier start, then an earlier end. Matches that are
+    /// equally good are ordered by their positions and unit, so that only
+    /// equal matches compare as equal.
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score
+            .cmp(&other.score)
+            .then(other.start.cmp(&self.start))
+            .then(other.end.cmp(&self.end))
+            .then_with(|| other.positions.cmp(&self.positions))
+            .then(self.unit.cmp(&other.unit))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fuzzy_find;
+
+    fn ranked(texts: &[&str], pattern: &str, tiebreak: &[Tiebreak]) -> Vec<usize> {
+        let mut keys: Vec<(RankKey, usize)> = texts
+            .iter()
+            .enumerate()
+            .filter_map(|(i, text)| {
+                fuzzy_find(text, pattern, false, true)
+                    .map(|m| (RankKey::new(tiebreak, text, i, &m), i))
+            })
+            .collect();
+        keys.sort();
+        keys.into_iter().map(|(_, i)| i).collect()
+    }
+
+    #[test]
+    fn test_tiebreaks() {
+        let texts = ["foo bar baz", "foo bar", "baz foo bar", "foo bar"];
+        assert_eq!(ranked(&texts, "foo", &[Tiebreak::Score]), vec![0, 1, 2, 3]);
+        assert_eq!(ranked(&texts, "foo", &[Tiebreak::Length]), vec![1, 3, 0, 2]);
+        assert_eq!(ranked(&texts, "bar", &[Tiebreak::Begin]), vec![0, 1, 3, 2]);
+        assert_eq!(ranked(&texts, "bar", &[Tiebreak::End]), vec![1, 2, 3, 0]);
+        assert_eq!(ranked(&texts, "foo", &[Tiebreak::Index]), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_chunk_tiebreak() {
+        let texts = ["src/foobar.rs x", "foo.rs src/long/path"];
+        assert_eq!(ranked(&texts, "foo", &[Tiebreak::Chunk]), vec![1, 0]);
+    }
+
+    #[test]
+    fn test_match_ord() {
+        let m = |score, start, end| Match {
+            start,
+            end,
+            score,
+            positions: vec![],
+            unit: Unit::Char,
+        };
+        let mut matches = vec![m(10, 3, 5), m(20, 4, 6), m(10, 1, 5), m(10, 1, 2)];
+        assert_eq!(matches.iter().max(), Some(&m(20, 4, 6)));
+        matches.sort_by(|a, b| b.cmp(a));
+        assert_eq!(
+            matches,
+            vec![m(20, 4, 6), m(10, 1, 2), m(10, 1, 5), m(10, 3, 5)]
+        );
+
+        let graphemes = Match {
+            unit: Unit::Grapheme,
+            ..m(10, 1, 2)
+        };
+        assert_ne!(graphemes.cmp(&m(10, 1, 2)), Ordering::Equal);
+    }
+}
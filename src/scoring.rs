@@ -0,0 +1,238 @@
+//! Scoring parameters and character-class bonuses.
+
+/// The scores and bonuses used by the matcher.
+///
+/// Matched characters earn `score_match` plus a bonus depending on where they
+/// occur in the text, and gaps between matched characters are penalized.
+///
+/// Three presets are provided, following fzf's `--scheme` option:
+///
+/// * [`Scoring::default`] for generic text.
+/// * [`Scoring::path`] for file paths.
+/// * [`Scoring::history`] for command history and other texts where
+///   whitespace and delimiters are no more significant than punctuation.
+///
+/// All fields are public, so a preset can be tuned for a specific domain:
+///
+/// ```
+/// use rizzer::{Matcher, MatcherConfig, Scoring};
+///
+/// let scoring = Scoring {
+///     score_gap_extension: -2,
+///     ..Scoring::default()
+/// };
+/// let mut matcher = Matcher::new(MatcherConfig {
+///     scoring,
+///     ..Default::default()
+/// });
+/// assert!(matcher.match_one("rust-lang", "rl").is_some());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Scoring {
+    /// Score awarded for every matched character.
+    pub score_match: i32,
+    /// Penalty for the first character of a gap.
+    pub score_gap_start: i32,
+    /// Penalty for every further character of a gap.
+    pub score_gap_extension: i32,
+    /// Bonus for a match at a word boundary, after a punctuation character.
+    pub bonus_boundary: i32,
+    /// Bonus for a match at a word boundary after whitespace.
+    pub bonus_boundary_white: i32,
+    /// Bonus for a match at a word boundary after a delimiter, such as `/`
+    /// or `:`. In path mode, the delimiters are the path separators.
+    pub bonus_boundary_delimiter: i32,
+    /// Bonus for every character matched in the final component of a path.
+    /// Only applies in path mode.
+    pub bonus_basename: i32,
+    /// Bonus for a lowercase to uppercase or a non-number to number
+    /// transition, as in `fooBar` or `utf8`.
+    pub bonus_camel123: i32,
+    /// Minimum bonus for every character of a run of consecutive matches
+    /// after the first. The characters of a run also inherit the bonus of
+    /// the character that started it.
+    pub bonus_consecutive: i32,
+    /// Multiplier applied to the bonus of the first pattern character.
+    pub bonus_first_char_multiplier: i32,
+}
+
+impl Default for Scoring {
+    fn default() -> Self {
+        const SCORE_MATCH: i32 = 16;
+        const SCORE_GAP_START: i32 = -3;
+        const SCORE_GAP_EXTENSION: i32 = -1;
+        Scoring {
+            score_match: SCORE_MATCH,
+            score_gap_start: SCORE_GAP_START,
+            score_gap_extension: SCORE_GAP_EXTENSION,
+            bonus_boundary: SCORE_MATCH / 2,
+            bonus_boundary_white: SCORE_MATCH / 2 + 2,
+            bonus_boundary_delimiter: SCORE_MATCH / 2 + 1,
+            bonus_basename: 0,
+            bonus_camel123: SCORE_MATCH / 2 - 1,
+            // A run of consecutive matches outweighs the gap it avoids.
+            bonus_consecutive: -(SCORE_GAP_START + SCORE_GAP_EXTENSION),
+            bonus_first_char_multiplier: 2,
+        }
+    }
+}
+
+impl Scoring {
+    /// Returns a scoring tuned for matching file paths.
+    ///
+    /// Path separators are stronger boundaries than whitespace, and matches
+    /// in the final path component are preferred. Use it together with
+    /// [`PathMode`](crate::PathMode).
+    pub fn path() -> Self {
+        let default = Scoring::default();
+        Scoring {
+            bonus_boundary_white: default.bonus_boundary,
+            bonus_boundary_delimiter: default.bonus_boundary + 2,
+            bonus_basename: 2,
+            ..default
+        }
+    }
+
+    /// Returns a scoring tuned for matching command history.
+    ///
+    /// Word boundaries after whitespace and delimiters earn the same bonus as
+    /// any other word boundary.
+    pub fn history() -> Self {
+        let default = Scoring::default();
+        Scoring {
+            bonus_boundary_white: default.bonus_boundary,
+            bonus_boundary_delimiter: default.bonus_boundary,
+            ..default
+        }
+    }
+
+    /// Calculates the bonus score based on the previous and current character classes.
+    ///
+    /// # Arguments
+    ///
+    /// * `prev_class` - The `CharClass` of the previous character.
+    /// * `curr_class` - The `CharClass` of the current character.
+    ///
+    /// # Returns
+    ///
+    /// The calculated bonus score as an `i32`.
+    pub(crate) fn bonus_for(&self, prev_class: &CharClass, curr_class: &CharClass) -> i32 {
+        if curr_class.is_word() {
+            match prev_class {
+                CharClass::White => return self.bonus_boundary_white,
+                CharClass::Delimiter => return self.bonus_boundary_delimiter,
+                CharClass::Punct => return self.bonus_boundary,
+                _ => {}
+            }
+        }
+
+        match (prev_class, curr_class) {
+            // Lower to upper case, as in `fooBar`.
+            (CharClass::Lower, CharClass::Upper) => self.bonus_camel123,
+            // Letter to number, as in `utf8`.
+            (prev, CharClass::Number) if *prev != CharClass::Number => self.bonus_camel123,
+            _ => 0,
+        }
+    }
+}
+
+/// Characters that delimit fields or path components.
+pub(crate) const DELIMITERS: &[char] = &['/', ',', ':', ';', '|'];
+
+/// Represents the character class for bonus calculation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CharClass {
+    White,
+    Lower,
+    Upper,
+    /// A letter without case, e.g. from a CJK script.
+    Letter,
+    Number,
+    Delimiter,
+    Punct,
+}
+
+impl CharClass {
+    /// Returns `true` for letters and numbers.
+    fn is_word(&self) -> bool {
+        matches!(
+            self,
+            CharClass::Lower | CharClass::Upper | CharClass::Letter | CharClass::Number
+        )
+    }
+}
+
+/// Determines the character class of a given character.
+///
+/// # Arguments
+///
+/// * `c` - The character to classify.
+/// * `delimiters` - The characters classified as `CharClass::Delimiter`.
+///
+/// # Returns
+///
+/// The `CharClass` of the input character.
+pub(crate) fn char_class(c: char, delimiters: &[char]) -> CharClass {
+    if c.is_whitespace() {
+        CharClass::White
+    } else if c.is_lowercase() {
+        CharClass::Lower
+    } else if c.is_uppercase() {
+        CharClass::Upper
+    } else if c.is_numeric() {
+        CharClass::Number
+    } else if c.is_alphabetic() {
+        CharClass::Letter
+    } else if delimiters.contains(&c) {
+        CharClass::Delimiter
+    } else {
+        CharClass::Punct
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bonuses(text: &str) -> Vec<i32> {
+        let scoring = Scoring::default();
+        let mut prev_class = CharClass::White;
+        text.chars()
+            .map(|c| {
+                let curr_class = char_class(c, DELIMITERS);
+                let bonus = scoring.bonus_for(&prev_class, &curr_class);
+                prev_class = curr_class;
+                bonus
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_camel_case_bonus() {
+        let camel = Scoring::default().bonus_camel123;
+        assert_eq!(bonuses("fooBar"), vec![10, 0, 0, camel, 0, 0]);
+        assert_eq!(bonuses("FOO"), vec![10, 0, 0]);
+    }
+
+    #[test]
+    fn test_number_bonus() {
+        let camel = Scoring::default().bonus_camel123;
+        assert_eq!(bonuses("utf8To16"), vec![10, 0, 0, camel, 0, 0, camel, 0]);
+        assert_eq!(bonuses("a-1"), vec![10, 0, 8]);
+        assert_eq!(bonuses("a/1"), vec![10, 0, 9]);
+    }
+
+    #[test]
+    fn test_presets() {
+        let default = Scoring::default();
+        assert!(default.bonus_boundary_white > default.bonus_boundary_delimiter);
+
+        let path = Scoring::path();
+        assert!(path.bonus_boundary_delimiter > path.bonus_boundary_white);
+        assert!(path.bonus_basename > 0);
+
+        let history = Scoring::history();
+        assert_eq!(history.bonus_boundary_white, history.bonus_boundary);
+        assert_eq!(history.bonus_boundary_delimiter, history.bonus_boundary);
+    }
+}
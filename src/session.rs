@@ -0,0 +1,222 @@
+//! Incremental matching for interactive search.
+
+use std::collections::HashMap;
+
+use crate::{Match, Matcher};
+
+/// A search over a fixed list of candidates, where the pattern changes one
+/// keystroke at a time.
+///
+/// The results of recent patterns are cached. When a pattern extends a
+/// pattern that was searched before, only the candidates that matched the
+/// shorter pattern are matched again, since a candidate that does not match a
+/// pattern cannot match any extension of it. Deleting characters goes back to
+/// a cached result without any matching.
+///
+/// The cache holds at most [`Session::DEFAULT_CACHE_CAPACITY`] matches in
+/// total, or the number set with [`Session::set_cache_capacity`]. When it is
+/// full, the results of the least recently searched patterns are evicted.
+///
+/// The pattern is always matched as a fuzzy pattern, as with
+/// [`Matcher::match_one`].
+///
+/// # Example
+///
+/// ```
+/// use rizzer::{Matcher, MatcherConfig, Session};
+///
+/// let mut session = Session::new(
+///     Matcher::new(MatcherConfig::default()),
+///     vec!["src/lib.rs", "src/matcher.rs", "README.md"],
+/// );
+/// assert_eq!(session.search("m").len(), 2);
+/// assert_eq!(session.search("ma").len(), 1);
+/// assert_eq!(session.search("m").len(), 2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Session<S> {
+    matcher: Matcher,
+    candidates: Vec<S>,
+    /// The ranked matches of recently searched patterns, with the time each
+    /// pattern was last searched.
+    cache: HashMap<String, (u64, Vec<(usize, Match)>)>,
+    /// The number of searches so far, used as a clock.
+    clock: u64,
+    /// The total number of matches the cache may hold.
+    cache_capacity: usize,
+}
+
+impl<S> Session<S> {
+    /// The default total number of matches the cache may hold.
+    pub const DEFAULT_CACHE_CAPACITY: usize = 1 << 20;
+}
+
+impl<S: AsRef<str>> Session<S> {
+    /// Creates a session searching `candidates` with `matcher`.
+    pub fn new(matcher: Matcher, candidates: Vec<S>) -> Self {
+        Session {
+            matcher,
+            candidates,
+            cache: HashMap::new(),
+            clock: 0,
+            cache_capacity: Self::DEFAULT_CACHE_CAPACITY,
+        }
+    }
+
+    /// Sets the total number of matches the cache may hold.
+    ///
+    /// The results of the latest pattern are always kept, even if they
+    /// exceed the capacity on their own.
+    pub fn set_cache_capacity(&mut self, capacity: usize) {
+        self.cache_capacity = capacity;
+        self.evict();
+    }
+
+    /// Returns the candidates searched by this session.
+    pub fn candidates(&self) -> &[S] {
+        &self.candidates
+    }
+
+    /// Returns the matcher used by this session.
+    pub fn matcher(&self) -> &Matcher {
+        &self.matcher
+    }
+
+    /// Matches `pattern` against the candidates.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - The pattern to search for.
+    ///
+    /// # Returns
+    ///
+    /// The index and match of every candidate that matched, from best to
+    /// worst, as returned by [`Matcher::match_all`].
+    pub fn search(&mut self, pattern: &str) -> &[(usize, Match)] {
+        self.clock += 1;
+        match self.cache.get_mut(pattern) {
+            Some((searched, _)) => *searched = self.clock,
+            None => {
+                let matches = self.search_uncached(pattern);
+                self.cache
+                    .insert(pattern.to_string(), (self.clock, matches));
+                self.evict();
+            }
+        }
+        &self.cache[pattern].1
+    }
+
+    /// Forgets the results of all previous patterns.
+    pub fn clear_cache(&mut self) {
+        self.cache.clear();
+    }
+
+    /// Evicts the least recently searched patterns, other than the latest,
+    /// until the cache holds at most `cache_capacity` matches.
+    fn evict(&mut self) {
+        let mut total: usize = self.cache.values().map(|(_, matches)| matches.len()).sum();
+        while total > self.cache_capacity {
+            let oldest = self
+                .cache
+                .iter()
+                .filter(|(_, (searched, _))| *searched != self.clock)
+                .min_by_key(|(_, (searched, _))| *searched)
+                .map(|(pattern, _)| pattern.clone());
+            let Some(oldest) = oldest else {
+                break;
+            };
+            if let Some((_, matches)) = self.cache.remove(&oldest) {
+                total -= matches.len();
+            }
+        }
+    }
+
+    /// Matches `pattern` against the candidates that matched its longest
+    /// cached prefix, or against all candidates if there is none.
+    fn search_uncached(&mut self, pattern: &str) -> Vec<(usize, Match)> {
+        let survivors: Option<Vec<usize>> = pattern
+            .char_indices()
+            .rev()
+            .find_map(|(end, _)| self.cache.get(&pattern[..end]))
+            .map(|(_, matches)| matches.iter().map(|(i, _)| *i).collect());
+
+        self.matcher.set_pattern(pattern);
+        let track_positions = self.matcher.config().track_positions;
+        let mut matches: Vec<(usize, Match)> = match survivors {
+            Some(mut survivors) => {
+                survivors.sort_unstable();
+                survivors
+                    .into_iter()
+                    .filter_map(|i| {
+                        self.matcher
+                            .match_text(self.candidates[i].as_ref(), track_positions)
+                            .map(|m| (i, m))
+                    })
+                    .collect()
+            }
+            None => self.matcher.match_many(&self.candidates, pattern),
+        };
+
+        self.matcher.sort_matches(&self.candidates, &mut matches);
+        matches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CaseMatching, MatcherConfig};
+
+    #[test]
+    fn test_session_matches_match_all() {
+        let candidates: Vec<String> = (0..300)
+            .map(|i| format!("Item_{}/File{}.rs", i % 13, i * 17 % 89))
+            .collect();
+        let config = MatcherConfig {
+            case_matching: CaseMatching::Smart,
+            ..Default::default()
+        };
+        let mut session = Session::new(Matcher::new(config.clone()), candidates.clone());
+        let mut matcher = Matcher::new(config);
+
+        for pattern in ["", "i", "i1", "i1F", "i1", "i", "i7", "i7/", "i7/f8", "x"] {
+            let expected = matcher.match_all(&candidates, pattern);
+            assert_eq!(session.search(pattern), expected, "{pattern:?}");
+        }
+    }
+
+    #[test]
+    fn test_session_narrows_to_survivors() {
+        let mut session = Session::new(Matcher::new(MatcherConfig::default()), vec!["abc", "xyz"]);
+        assert_eq!(session.search("a").len(), 1);
+
+        // Candidates that did not match "a" are not matched again.
+        session.candidates[1] = "abz";
+        assert_eq!(session.search("ab").len(), 1);
+
+        session.clear_cache();
+        assert_eq!(session.search("ab").len(), 2);
+    }
+
+    #[test]
+    fn test_session_cache_capacity() {
+        let candidates = vec!["ab", "abc", "abd", "xyz"];
+        let mut session = Session::new(Matcher::new(MatcherConfig::default()), candidates);
+        session.set_cache_capacity(5);
+
+        session.search("a");
+        session.search("ab");
+        assert_eq!(session.cache.len(), 1);
+
+        // The latest results are kept even when they exceed the capacity.
+        session.set_cache_capacity(2);
+        assert!(session.cache.contains_key("ab"));
+        session.search("abc");
+        session.search("abd");
+        assert_eq!(session.cache.len(), 2);
+        assert!(session.cache.contains_key("abc") && session.cache.contains_key("abd"));
+
+        // Evicted results are computed again.
+        assert_eq!(session.search("a").len(), 3);
+    }
+}
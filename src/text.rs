@@ -0,0 +1,363 @@
+//! Preparation of texts and patterns for matching.
+//!
+//! Case folding and normalization can change the number of characters in a
+//! string (e.g. 'İ' lowercases to "i̇"), and byte lengths never line up with
+//! character counts outside of ASCII. To keep lengths, bonuses and positions
+//! consistent, the matcher works on an [`IndexedText`], which records for
+//! every character it matches against where that character came from in the
+//! original string. With the `grapheme` feature, the original string can be
+//! divided into grapheme clusters instead of characters.
+
+use std::iter::once;
+
+use caseless::Caseless;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::matcher::MatcherConfig;
+use crate::scoring::{char_class, CharClass, DELIMITERS};
+
+#[cfg(feature = "grapheme")]
+use unicode_segmentation::UnicodeSegmentation;
+
+/// How thoroughly characters are folded before matching.
+///
+/// Each level includes the ones before it. The same folding is applied to the
+/// text and to the pattern, and matched positions always refer to the
+/// characters of the original text. Whether case is folded at all is decided
+/// by [`CaseMatching`](crate::CaseMatching).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Normalization {
+    /// Characters are compared as they are, except that case-insensitive
+    /// matching lowercases them.
+    None,
+    /// Case-insensitive matching uses full Unicode case folding, so 'ß'
+    /// matches "ss".
+    CaseFold,
+    /// Characters are decomposed (NFD) and combining marks are removed, so
+    /// 'é' matches 'e'.
+    #[default]
+    StripDiacritics,
+    /// Compatibility characters are decomposed as well (NFKD), so 'ﬁ' matches
+    /// "fi" and fullwidth 'Ａ' matches 'A'.
+    Compatibility,
+    /// Characters are transliterated to ASCII, so "Москва" matches "moskva"
+    /// and "Αθήνα" matches "athina". Characters without a transliteration are
+    /// folded as with `Compatibility`.
+    ///
+    /// This needs the `transliterate` cargo feature. Without it, this level
+    /// behaves like `Compatibility`.
+    Transliterate,
+}
+
+impl From<bool> for Normalization {
+    /// Converts a `normalize` flag into `StripDiacritics` or `None`.
+    fn from(normalize: bool) -> Self {
+        if normalize {
+            Normalization::StripDiacritics
+        } else {
+            Normalization::None
+        }
+    }
+}
+
+/// A text prepared for matching.
+///
+/// All vectors have one entry per character to match against.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct IndexedText {
+    /// The characters to match against, after case folding and normalization.
+    pub(crate) chars: Vec<char>,
+    /// The bonus for matching each character.
+    pub(crate) bonus: Vec<i32>,
+    /// The index of the character in the original string each character was
+    /// derived from.
+    pub(crate) sources: Vec<usize>,
+}
+
+impl IndexedText {
+    /// Returns the number of characters to match against.
+    pub(crate) fn len(&self) -> usize {
+        self.chars.len()
+    }
+
+    /// Replaces the contents of this text with `text`, reusing the buffers.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The original text.
+    /// * `case_sensitive` - Whether the match should be case-sensitive.
+    /// * `config` - The configuration of the matcher.
+    pub(crate) fn index(&mut self, text: &str, case_sensitive: bool, config: &MatcherConfig) {
+        self.chars.clear();
+        self.bonus.clear();
+        self.sources.clear();
+
+        let scoring = &config.scoring;
+        let (delimiters, mut prev_class, basename) = match config.path_mode.separators() {
+            // The start of a path is treated like the start of a component.
+            Some(separators) => (
+                separators,
+                CharClass::Delimiter,
+                basename_start(text, separators),
+            ),
+            None => (DELIMITERS, CharClass::White, usize::MAX),
+        };
+
+        // The bonuses of a unit of the text starting with `c`. Only the first
+        // character derived from the unit sits on a boundary.
+        let mut bonus_at = |offset: usize, c: char| {
+            let curr_class = char_class(c, delimiters);
+            let boundary = scoring.bonus_for(&prev_class, &curr_class);
+            prev_class = curr_class;
+            let basename = if offset >= basename {
+                scoring.bonus_basename
+            } else {
+                0
+            };
+            (boundary + basename, basename)
+        };
+
+        #[cfg(feature = "grapheme")]
+        if config.graphemes {
+            for (source, (offset, grapheme)) in text.grapheme_indices(true).enumerate() {
+                let Some(first) = grapheme.chars().next() else {
+                    continue;
+                };
+                let bonus = bonus_at(offset, first);
+                self.push_unit(grapheme.chars(), source, bonus, case_sensitive, config);
+            }
+            return;
+        }
+
+        for (source, (offset, c)) in text.char_indices().enumerate() {
+            let bonus = bonus_at(offset, c);
+            self.push_unit(once(c), source, bonus, case_sensitive, config);
+        }
+    }
+
+    /// Appends the characters to match against for one unit of the original
+    /// text, a character or a grapheme cluster.
+    ///
+    /// # Arguments
+    ///
+    /// * `unit` - The characters of the unit.
+    /// * `source` - The index of the unit in the original text.
+    /// * `bonus` - The bonus for matching the first character derived from the
+    ///   unit, and for matching any other.
+    /// * `case_sensitive` - Whether the match should be case-sensitive.
+    /// * `config` - The configuration of the matcher.
+    fn push_unit(
+        &mut self,
+        unit: impl Iterator<Item = char>,
+        source: usize,
+        (bonus, inner_bonus): (i32, i32),
+        case_sensitive: bool,
+        config: &MatcherConfig,
+    ) {
+        let start = self.chars.len();
+        for c in unit {
+            fold_char(c, case_sensitive, config.normalization, &mut self.chars);
+        }
+        for i in start..self.chars.len() {
+            self.bonus
+                .push(if i == start { bonus } else { inner_bonus });
+            self.sources.push(source);
+        }
+    }
+}
+
+/// Returns the units of `text` that positions count: its grapheme clusters
+/// if `graphemes` is set, its characters otherwise. Without the `grapheme`
+/// feature, `graphemes` is ignored.
+pub(crate) fn units(text: &str, graphemes: bool) -> impl Iterator<Item = &str> {
+    let graphemes = graphemes && cfg!(feature = "grapheme");
+    #[cfg(feature = "grapheme")]
+    let clusters = graphemes.then(|| text.graphemes(true));
+    #[cfg(not(feature = "grapheme"))]
+    let clusters = graphemes.then(std::iter::empty::<&str>);
+
+    let chars = (!graphemes).then(|| text.char_indices().map(|(i, c)| &text[i..i + c.len_utf8()]));
+    clusters
+        .into_iter()
+        .flatten()
+        .chain(chars.into_iter().flatten())
+}
+
+/// Finds the start of the final component of a path.
+///
+/// Trailing separators are ignored, so the final component of `src/foo/` is
+/// `foo/`.
+///
+/// # Returns
+///
+/// The byte offset of the final component.
+fn basename_start(path: &str, separators: &[char]) -> usize {
+    let trimmed = path.trim_end_matches(separators);
+    match trimmed
+        .char_indices()
+        .rfind(|(_, c)| separators.contains(c))
+    {
+        Some((i, separator)) => i + separator.len_utf8(),
+        None => 0,
+    }
+}
+
+/// Prepares `pattern` for matching, replacing the contents of `out`.
+///
+/// # Arguments
+///
+/// * `pattern` - The original pattern.
+/// * `case_sensitive` - Whether the match should be case-sensitive.
+/// * `normalization` - How thoroughly characters are folded.
+/// * `out` - The buffer receiving the characters to match.
+pub(crate) fn fold_pattern(
+    pattern: &str,
+    case_sensitive: bool,
+    normalization: Normalization,
+    out: &mut Vec<char>,
+) {
+    out.clear();
+    for c in pattern.chars() {
+        fold_char(c, case_sensitive, normalization, out);
+    }
+}
+
+/// Appends the characters to match against for `c` to `out`.
+fn fold_char(c: char, case_sensitive: bool, normalization: Normalization, out: &mut Vec<char>) {
+    out.extend(folded(c, case_sensitive, normalization));
+}
+
+/// Returns the characters to match against for `c`.
+///
+/// The character is first transliterated or decomposed, then its case is
+/// folded, and finally combining marks are removed. Folding the case after
+/// decomposing catches compatibility characters that decompose to uppercase
+/// letters, such as 'ℌ', and removing marks last catches the marks introduced
+/// by case folding, as in 'İ' → "i̇". Any step may yield no characters or
+/// several.
+///
+/// # Arguments
+///
+/// * `c` - The original character.
+/// * `case_sensitive` - Whether the match should be case-sensitive.
+/// * `normalization` - How thoroughly characters are folded.
+pub(crate) fn folded(
+    c: char,
+    case_sensitive: bool,
+    normalization: Normalization,
+) -> impl Iterator<Item = char> {
+    #[cfg(feature = "transliterate")]
+    let ascii = (normalization == Normalization::Transliterate)
+        .then(|| any_ascii::any_ascii_char(c))
+        .filter(|ascii| !ascii.is_empty())
+        .map(str::chars);
+    #[cfg(not(feature = "transliterate"))]
+    let ascii: Option<std::str::Chars<'static>> = None;
+
+    let decomposed = match normalization {
+        _ if ascii.is_some() => None,
+        Normalization::None | Normalization::CaseFold => None,
+        Normalization::StripDiacritics => Some(once(c).nfd()),
+        _ => Some(once(c).nfkd()),
+    };
+    let same = (ascii.is_none() && decomposed.is_none()).then_some(c);
+    let strip_marks = normalization >= Normalization::StripDiacritics;
+
+    ascii
+        .into_iter()
+        .flatten()
+        .chain(decomposed.into_iter().flatten())
+        .chain(same)
+        .flat_map(move |c| fold_case(c, case_sensitive, normalization))
+        .filter(move |&c| !(strip_marks && is_combining_mark(c)))
+}
+
+/// Folds the case of `c` for matching, unless the match is case-sensitive.
+fn fold_case(
+    c: char,
+    case_sensitive: bool,
+    normalization: Normalization,
+) -> impl Iterator<Item = char> {
+    let (folded, lower, same) = if case_sensitive {
+        (None, None, Some(c))
+    } else if normalization >= Normalization::CaseFold {
+        (Some(once(c).default_case_fold()), None, None)
+    } else {
+        (None, Some(c.to_lowercase()), None)
+    };
+    folded
+        .into_iter()
+        .flatten()
+        .chain(lower.into_iter().flatten())
+        .chain(same)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_expanding_lowercase() {
+        let mut text = IndexedText::default();
+        let config = MatcherConfig {
+            normalization: Normalization::None,
+            ..Default::default()
+        };
+        text.index("İx", false, &config);
+        assert_eq!(text.chars, vec!['i', '\u{307}', 'x']);
+        assert_eq!(text.sources, vec![0, 0, 1]);
+        assert_eq!(text.bonus.len(), text.len());
+        assert_eq!(text.bonus[1], 0);
+    }
+
+    #[test]
+    fn test_folded_levels() {
+        let fold = |c, normalization| -> String { folded(c, false, normalization).collect() };
+        assert_eq!(fold('ß', Normalization::None), "ß");
+        assert_eq!(fold('ß', Normalization::CaseFold), "ss");
+        assert_eq!(fold('İ', Normalization::CaseFold), "i\u{307}");
+        assert_eq!(fold('İ', Normalization::StripDiacritics), "i");
+        assert_eq!(fold('É', Normalization::CaseFold), "é");
+        assert_eq!(fold('É', Normalization::StripDiacritics), "e");
+        assert_eq!(fold('ﬁ', Normalization::CaseFold), "fi");
+        assert_eq!(fold('Ａ', Normalization::StripDiacritics), "ａ");
+        assert_eq!(fold('Ａ', Normalization::Compatibility), "a");
+        assert_eq!(fold('ℌ', Normalization::Compatibility), "h");
+        assert_eq!(fold('\u{301}', Normalization::StripDiacritics), "");
+
+        let sensitive: String = folded('Ｅ', true, Normalization::Compatibility).collect();
+        assert_eq!(sensitive, "E");
+    }
+
+    #[cfg(feature = "transliterate")]
+    #[test]
+    fn test_folded_transliterate() {
+        let fold = |text: &str| -> String {
+            text.chars()
+                .flat_map(|c| folded(c, false, Normalization::Transliterate))
+                .collect()
+        };
+        assert_eq!(fold("Москва"), "moskva");
+        assert_eq!(fold("Αθήνα"), "athina");
+        assert_eq!(fold("Crème"), "creme");
+        assert_eq!(fold("ascii"), "ascii");
+    }
+
+    #[cfg(not(feature = "transliterate"))]
+    #[test]
+    fn test_folded_transliterate_without_feature() {
+        let fold: String = folded('ﬁ', false, Normalization::Transliterate).collect();
+        assert_eq!(fold, "fi");
+        assert!(folded('Ж', false, Normalization::Transliterate).eq(['ж']));
+    }
+
+    #[test]
+    fn test_basename_start() {
+        assert_eq!(basename_start("src/matcher.rs", &['/']), 4);
+        assert_eq!(basename_start("src/foo/", &['/']), 4);
+        assert_eq!(basename_start("matcher.rs", &['/']), 0);
+        assert_eq!(basename_start("a\\b/c", &['/', '\\']), 4);
+        assert_eq!(basename_start("ä/ö", &['/']), 3);
+    }
+}